@@ -1,16 +1,27 @@
 //This file has analysis functions for the health graph's health trends and statistics
 
-use crate::graph::{HealthGraph, PersonNode};
-use std::collections::{HashMap, VecDeque};
+use crate::graph::{Adjacency, PersonNode};
+use crate::schema::Response;
+use crate::codebook::CodeBook;
+use std::cmp::Reverse;
+use std::collections::{hash_map::Entry, BTreeMap, BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+use std::fmt;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Serialize;
+use serde::ser::{SerializeStruct, Serializer};
+
+//the analysis functions run on any Adjacency: the HealthGraph or its frozen CsrGraph
 
 //computes the number of connections(degree) for each node in the graph and returns a vector of tuples
-pub fn compute_degrees(graph: &HealthGraph) -> Vec<(usize, usize)> {
-    graph.nodes.keys().map(|id| (*id, graph.degree(*id))).collect()
+pub fn compute_degrees<G: Adjacency>(graph: &G) -> Vec<(G::Id, usize)> {
+    graph.node_ids().map(|id| (id, graph.degree(id))).collect()
 }
 //computes the average degree for all of the nodes in the graph and returns it
-pub fn average_degree(graph: &HealthGraph) -> f64 {
-    let total_degree: usize = graph.nodes.keys().map(|id| graph.degree(*id)).sum(); //sum of all degrees
-    let num_nodes = graph.nodes.len();
+pub fn average_degree<G: Adjacency>(graph: &G) -> f64 {
+    let total_degree: usize = graph.node_ids().map(|id| graph.degree(id)).sum(); //sum of all degrees
+    let num_nodes = graph.node_count();
     if num_nodes == 0 {
         0.0
     }
@@ -19,22 +30,19 @@ pub fn average_degree(graph: &HealthGraph) -> f64 {
     }
 }
 //computes the node with the highest degree in the graph and returns a tuple of the node id and its degree
-pub fn node_w_highest_degree(graph: &HealthGraph) -> Option<(usize, usize)> {
-    compute_degrees(graph).into_iter().max_by_key(|&(_, degree)| degree)
+//ties go to the smallest id so the result does not depend on the order of the HashMap
+pub fn node_w_highest_degree<G: Adjacency>(graph: &G) -> Option<(G::Id, usize)> {
+    compute_degrees(graph).into_iter().max_by_key(|&(id, degree)| (degree, Reverse(id)))
 }
 //computes the average shortest path length in the graph and returns it
-pub fn average_shortest_path_length(graph: &HealthGraph) -> f64 {
+pub fn average_shortest_path_length<G: Adjacency>(graph: &G) -> f64 {
     let mut total_distance = 0;
     let mut count = 0;
-    let ids: Vec<usize> = graph.nodes.keys().copied().collect(); //collect the ids of all nodes
+    let ids: Vec<G::Id> = graph.node_ids().collect(); //collect the ids of all nodes
     for &start_id in &ids { 
-        let distances = bfs_distances(graph, start_id); //compute distances from start_id to all other nodes
-        for &dist in distances.values() {
-            if dist > 0 { //ignore the distance to itself
-                total_distance += dist;
-                count += 1;
-            }
-        }
+        let (distance, reached) = distance_total(graph, start_id); //compute distances from start_id to all other nodes
+        total_distance += distance;
+        count += reached;
     }
     if count == 0 {
         0.0
@@ -43,55 +51,475 @@ pub fn average_shortest_path_length(graph: &HealthGraph) -> f64 {
         total_distance as f64 / count as f64
     }
 }
+///estimate of the average shortest path length from the BFS runs of a sample of source nodes
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PathEstimate {
+    pub mean: f64,
+    pub margin: f64, //half width of the 95% confidence interval, 0 if every node was a source, infinite with a single source
+    pub sources: usize, //number of BFS sources
+}
+
+impl PathEstimate {
+    /// Returns the bounds of the 95% confidence interval.
+    pub fn interval(&self) -> (f64, f64) {
+        (self.mean - self.margin, self.mean + self.margin)
+    }
+}
+//z value of a two-sided 95% confidence interval
+const Z_95: f64 = 1.959963984540054;
+/// Estimates the average shortest path length from the BFS runs of `sources` nodes drawn without replacement,
+/// instead of a run from every node, O(k·m) instead of O(n·m).
+/// The estimate is the distance sum over the number of reached pairs of the sampled sources(a ratio estimator),
+/// the interval comes from the variance of that ratio with a finite population correction.
+/// The same graph, number of sources and RNG state always give the same estimate, with every node as a source it is exact.
+pub fn estimate_average_shortest_path_length<G: Adjacency>(graph: &G, sources: usize, rng: &mut impl Rng) -> PathEstimate {
+    let totals: Vec<(usize, usize)> = sample_sources(graph, sources, rng).into_iter()
+        .map(|start_id| distance_total(graph, start_id))
+        .collect();
+    path_estimate(graph.node_count(), &totals)
+}
+//draws the BFS sources of an estimate, the ids are sorted first so the draw does not depend on the order of the HashMap
+pub(crate) fn sample_sources<G: Adjacency>(graph: &G, sources: usize, rng: &mut impl Rng) -> Vec<G::Id> {
+    let mut ids: Vec<G::Id> = graph.node_ids().collect();
+    ids.sort();
+    let (sampled, _) = ids.partial_shuffle(rng, sources);
+    sampled.to_vec()
+}
+//returns the sum of the distances from a node to every node it reaches and the number of those nodes
+pub(crate) fn distance_total<G: Adjacency>(graph: &G, start: G::Id) -> (usize, usize) {
+    let reached = bfs_distances(graph, start).into_values().filter(|&dist| dist > 0); //ignore the distance to itself
+    reached.fold((0, 0), |(total, count), dist| (total + dist, count + 1))
+}
+//combines the distance totals of the sampled sources of a graph of `nodes` nodes into an estimate
+pub(crate) fn path_estimate(nodes: usize, totals: &[(usize, usize)]) -> PathEstimate {
+    let sources = totals.len();
+    let total_distance: usize = totals.iter().map(|&(distance, _)| distance).sum();
+    let count: usize = totals.iter().map(|&(_, reached)| reached).sum();
+    if count == 0 {
+        return PathEstimate { mean: 0.0, margin: 0.0, sources };
+    }
+    let mean = total_distance as f64 / count as f64;
+    let margin = if sources >= nodes {
+        0.0 //every node was a source, the mean is exact
+    } else if sources < 2 {
+        f64::INFINITY //the spread between sources cannot be measured
+    } else {
+        //variance of a ratio estimator: the spread of the residuals distance - mean * reached between sources
+        let residuals: f64 = totals.iter().map(|&(distance, reached)| (distance as f64 - mean * reached as f64).powi(2)).sum();
+        let spread = residuals / (sources - 1) as f64;
+        let reached = count as f64 / sources as f64; //mean number of nodes reached by a source
+        let correction = 1.0 - sources as f64 / nodes as f64; //sampling without replacement from a finite population
+        Z_95 * (correction * spread / sources as f64).sqrt() / reached
+    };
+    PathEstimate { mean, margin, sources }
+}
+//computes the strength(sum of the edge weights) of each node in the graph and returns a vector of tuples
+pub fn compute_strengths<G: Adjacency>(graph: &G) -> Vec<(G::Id, f64)> {
+    graph.node_ids().map(|id| (id, graph.strength(id))).collect()
+}
+//computes the average strength for all of the nodes in the graph and returns it
+pub fn average_strength<G: Adjacency>(graph: &G) -> f64 {
+    let total_strength: f64 = graph.node_ids().map(|id| graph.strength(id)).sum(); //sum of all strengths
+    let num_nodes = graph.node_count();
+    if num_nodes == 0 {
+        0.0
+    }
+    else {
+        total_strength / num_nodes as f64
+    }
+}
+//computes the node with the highest strength in the graph and returns a tuple of the node id and its strength
+//ties go to the smallest id like node_w_highest_degree
+pub fn node_w_highest_strength<G: Adjacency>(graph: &G) -> Option<(G::Id, f64)> {
+    compute_strengths(graph).into_iter().max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)))
+}
+//computes the average weighted shortest path length in the graph and returns it
+//the length of an edge is 1/weight, so strongly similar people are close, unreachable pairs are ignored
+pub fn average_weighted_shortest_path_length<G: Adjacency>(graph: &G) -> f64 {
+    let mut total_distance = 0.0;
+    let mut count = 0;
+    let mut ids: Vec<G::Id> = graph.node_ids().collect(); //collect the ids of all nodes
+    ids.sort(); //sum in the same order on every run
+    for &start_id in &ids {
+        let distances = dijkstra_distances(graph, start_id); //compute distances from start_id to all other nodes
+        let mut targets: Vec<_> = distances.into_iter().filter(|&(id, _)| id != start_id).collect(); //ignore the distance to itself
+        targets.sort_by_key(|&(id, _)| id);
+        for (_, dist) in targets {
+            total_distance += dist;
+            count += 1;
+        }
+    }
+    if count == 0 {
+        0.0
+    }
+    else {
+        total_distance / count as f64
+    }
+}
+///connected components of a graph, numbered from the largest(ties go to the component with the smallest id)
+#[derive(Debug, Clone)]
+pub struct Components<Id> {
+    pub component_of: HashMap<Id, usize>, //component number of each node
+    pub sizes: Vec<usize>, //number of nodes of each component, largest first
+}
+
+impl<Id: Copy + Eq + Hash> Components<Id> {
+    /// Returns the number of components, isolated nodes count as components of one node.
+    pub fn count(&self) -> usize {
+        self.sizes.len()
+    }
+    /// Returns the component number of a node, None if it is not in the graph.
+    pub fn component(&self, id: Id) -> Option<usize> {
+        self.component_of.get(&id).copied()
+    }
+    /// Returns the share of the nodes in the largest component, 0.0 for an empty graph.
+    pub fn giant_fraction(&self) -> f64 {
+        let nodes: usize = self.sizes.iter().sum();
+        if nodes == 0 {
+            0.0
+        } else {
+            self.sizes[0] as f64 / nodes as f64
+        }
+    }
+    /// Returns the number of components of each size.
+    pub fn size_distribution(&self) -> BTreeMap<usize, usize> {
+        let mut distribution = BTreeMap::new();
+        for &size in &self.sizes {
+            *distribution.entry(size).or_insert(0) += 1;
+        }
+        distribution
+    }
+    /// Returns the number of pairs of nodes with no path between them,
+    /// the pairs left out of the average shortest path length.
+    pub fn unreachable_pairs(&self) -> usize {
+        let nodes: usize = self.sizes.iter().sum();
+        let pairs = |size: usize| size * size.saturating_sub(1) / 2;
+        pairs(nodes) - self.sizes.iter().map(|&size| pairs(size)).sum::<usize>()
+    }
+}
+//finds the connected components of the graph with a BFS from every node not reached yet
+pub fn connected_components<G: Adjacency>(graph: &G) -> Components<G::Id> {
+    let mut ids: Vec<G::Id> = graph.node_ids().collect();
+    ids.sort(); //number the components in the same order on every run
+    let mut found: HashMap<G::Id, usize> = HashMap::new();
+    let mut sizes = Vec::new();
+    for &start_id in &ids {
+        if found.contains_key(&start_id) {
+            continue; //already in a component
+        }
+        let reached = bfs_distances(graph, start_id);
+        sizes.push(reached.len());
+        found.extend(reached.into_keys().map(|id| (id, sizes.len() - 1)));
+    }
+    let (rank, sizes) = largest_first(&sizes);
+    Components {
+        component_of: found.into_iter().map(|(id, component)| (id, rank[component])).collect(),
+        sizes,
+    }
+}
+//renumbers groups(components, communities) numbered in the order of their smallest id from the largest
+//the sort is stable so ties keep the order of their smallest id
+//returns the new number of every group and the sizes of the groups, largest first
+pub(crate) fn largest_first(sizes: &[usize]) -> (Vec<usize>, Vec<usize>) {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&group| Reverse(sizes[group]));
+    let mut rank = vec![0; sizes.len()];
+    for (new, &old) in order.iter().enumerate() {
+        rank[old] = new;
+    }
+    (rank, order.iter().map(|&group| sizes[group]).collect())
+}
+//computes the average shortest path length inside each component and returns it by component number
+//components of a single node have no pairs and get 0
+pub fn average_shortest_path_length_by_component<G: Adjacency>(graph: &G, components: &Components<G::Id>) -> Vec<f64> {
+    let mut totals = vec![(0, 0); components.count()];
+    for start_id in graph.node_ids() {
+        let (distance, reached) = distance_total(graph, start_id); //a BFS only reaches the component of its source
+        let total = &mut totals[components.component_of[&start_id]];
+        total.0 += distance;
+        total.1 += reached;
+    }
+    totals.into_iter()
+        .map(|(distance, count)| if count == 0 { 0.0 } else { distance as f64 / count as f64 })
+        .collect()
+}
+///distances between the nodes of a graph, from a BFS out of every node
+///eccentricities are measured inside the connected component of each node, so the diameter is that of the widest component
+#[derive(Debug, Clone)]
+pub struct DistanceStats<Id> {
+    pub eccentricity: HashMap<Id, usize>, //largest distance from each node to a node it reaches, 0 for isolated nodes
+    pub diameter: usize, //largest eccentricity
+    pub radius: usize, //smallest eccentricity of the nodes with a neighbor, 0 if the graph has no edges
+    pub center: Vec<Id>, //nodes with a neighbor whose eccentricity is the radius, sorted
+    pub periphery: Vec<Id>, //nodes with a neighbor whose eccentricity is the diameter, sorted
+    pub histogram: BTreeMap<usize, usize>, //number of connected pairs of nodes at each distance
+}
+
+impl<Id> DistanceStats<Id> {
+    /// Returns the number of connected pairs of nodes.
+    pub fn pairs(&self) -> usize {
+        self.histogram.values().sum()
+    }
+    /// Returns the mean distance of the connected pairs, the average shortest path length, 0.0 without pairs.
+    pub fn average(&self) -> f64 {
+        let total: usize = self.histogram.iter().map(|(&distance, &pairs)| distance * pairs).sum();
+        if total == 0 {
+            0.0
+        } else {
+            total as f64 / self.pairs() as f64
+        }
+    }
+}
+//computes the eccentricity of every node, the diameter, radius, center, periphery and distance histogram of the graph
+pub fn distance_stats<G: Adjacency>(graph: &G) -> DistanceStats<G::Id> {
+    let mut ids: Vec<G::Id> = graph.node_ids().collect();
+    ids.sort(); //center and periphery in the order of the ids
+    let mut eccentricity = HashMap::new();
+    let mut histogram = BTreeMap::new();
+    for &start_id in &ids {
+        let distances = bfs_distances(graph, start_id);
+        for &dist in distances.values().filter(|&&dist| dist > 0) { //ignore the distance to itself
+            *histogram.entry(dist).or_insert(0) += 1;
+        }
+        eccentricity.insert(start_id, distances.into_values().max().unwrap_or(0));
+    }
+    for pairs in histogram.values_mut() {
+        *pairs /= 2; //every pair was reached from both of its ends
+    }
+    let connected: Vec<G::Id> = ids.into_iter().filter(|&id| graph.degree(id) > 0).collect();
+    let diameter = eccentricity.values().copied().max().unwrap_or(0);
+    let radius = connected.iter().map(|id| eccentricity[id]).min().unwrap_or(0);
+    DistanceStats {
+        center: connected.iter().copied().filter(|id| eccentricity[id] == radius).collect(),
+        periphery: connected.iter().copied().filter(|id| eccentricity[id] == diameter).collect(),
+        eccentricity,
+        diameter,
+        radius,
+        histogram,
+    }
+}
+///size, degree and path statistics of a graph, the ids are those of its nodes
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphStats<Id = usize> {
+    pub nodes: usize,
+    pub edges: usize,
+    pub average_degree: f64,
+    pub average_strength: f64,
+    pub highest_degree_node: Option<Id>,
+    pub highest_degree: usize,
+    pub average_shortest_path_length: f64,
+    pub path_length_margin: Option<f64>, //half width of the 95% confidence interval of an estimated path length, None if exact
+    pub path_sources: Option<usize>, //number of source nodes the path length was estimated from, None if exact
+    pub components: usize,
+    pub giant_component_fraction: f64, //share of the nodes in the largest component
+    pub unreachable_pairs: usize, //pairs of nodes with no path between them, left out of the path length
+}
+//computes the statistics printed by the graph analysis
+pub fn graph_stats<G: Adjacency>(graph: &G) -> GraphStats<G::Id> {
+    graph_stats_with_path_length(graph, average_shortest_path_length(graph))
+}
+//computes the statistics with the average shortest path length estimated from `sources` sampled nodes
+pub fn estimated_graph_stats<G: Adjacency>(graph: &G, sources: usize, rng: &mut impl Rng) -> GraphStats<G::Id> {
+    let estimate = estimate_average_shortest_path_length(graph, sources, rng);
+    GraphStats {
+        path_length_margin: Some(estimate.margin),
+        path_sources: Some(estimate.sources),
+        ..graph_stats_with_path_length(graph, estimate.mean)
+    }
+}
+//computes the statistics of a graph whose average shortest path length is already known
+pub(crate) fn graph_stats_with_path_length<G: Adjacency>(graph: &G, path_length: f64) -> GraphStats<G::Id> {
+    let highest = node_w_highest_degree(graph);
+    let components = connected_components(graph);
+    GraphStats {
+        nodes: graph.node_count(),
+        edges: graph.edge_count(),
+        average_degree: average_degree(graph),
+        average_strength: average_strength(graph),
+        highest_degree_node: highest.map(|(id, _)| id),
+        highest_degree: highest.map_or(0, |(_, degree)| degree),
+        average_shortest_path_length: path_length,
+        path_length_margin: None,
+        path_sources: None,
+        components: components.count(),
+        giant_component_fraction: components.giant_fraction(),
+        unreachable_pairs: components.unreachable_pairs(),
+    }
+}
 //computes the distances from a starting node to all other nodes in the graph using BFS and returns a hashmap of distances
-fn bfs_distances(graph: &HealthGraph, start: usize) -> std::collections::HashMap<usize, usize> {
-    let mut distances = std::collections::HashMap::new();
+pub(crate) fn bfs_distances<G: Adjacency>(graph: &G, start: G::Id) -> HashMap<G::Id, usize> {
+    let mut distances = HashMap::new();
     let mut queue = VecDeque::new();
     distances.insert(start, 0); //distance to itself is 0
     queue.push_back(start);
     while let Some(current) = queue.pop_front() { //pop the first element from the queue
         let current_distance = distances[&current];
-        if let Some(neighbors) = graph.neighbors(current) { //get the neighbors of the current node
-            for &neighbor in neighbors {
-                if !distances.contains_key(&neighbor) { //if the neighbor has not been visited
-                    distances.insert(neighbor, current_distance + 1); //set the distance to the neighbor
-                    queue.push_back(neighbor); //add the neighbor to the queue
-                }
+        for neighbor in graph.neighbor_ids(current) { //get the neighbors of the current node
+            if let Entry::Vacant(entry) = distances.entry(neighbor) { //if the neighbor has not been visited
+                entry.insert(current_distance + 1); //set the distance to the neighbor
+                queue.push_back(neighbor); //add the neighbor to the queue
             }
         }
     }
     distances
 }
-//analyzes the health conditions of people based on their income and food security and prints the results
-pub fn analyze_health_by_income_and_food_security(people: &[PersonNode]) {
-    let mut groups: HashMap<(u8, u8), Vec<&PersonNode>> = HashMap::new(); //group people by income and food security
+//a node waiting in the Dijkstra queue, ordered so the BinaryHeap pops the closest node first
+struct Queued<Id> {
+    distance: f64,
+    id: Id,
+}
+
+impl<Id: Ord> Ord for Queued<Id> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.distance.total_cmp(&self.distance).then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+impl<Id: Ord> PartialOrd for Queued<Id> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Id: Ord> PartialEq for Queued<Id> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl<Id: Ord> Eq for Queued<Id> {}
+
+//computes the weighted distances from a starting node to all other nodes in the graph using Dijkstra and returns a hashmap of distances
+//the length of an edge is 1/weight
+fn dijkstra_distances<G: Adjacency>(graph: &G, start: G::Id) -> HashMap<G::Id, f64> {
+    let mut distances = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    distances.insert(start, 0.0); //distance to itself is 0
+    queue.push(Queued { distance: 0.0, id: start });
+    while let Some(Queued { distance, id: current }) = queue.pop() { //pop the closest node from the queue
+        if distance > distances[&current] {
+            continue; //a shorter path to this node was already handled
+        }
+        for (neighbor, weight) in graph.weighted_neighbors(current) { //get the neighbors of the current node
+            let candidate = distance + 1.0 / weight;
+            if distances.get(&neighbor).is_none_or(|&known| candidate < known) { //a shorter path to the neighbor
+                distances.insert(neighbor, candidate);
+                queue.push(Queued { distance: candidate, id: neighbor });
+            }
+        }
+    }
+    distances
+}
+//computes the percentage of people answering yes among those with a valid answer, None if nobody answered
+fn percent_yes(group: &[&PersonNode], answer: impl Fn(&PersonNode) -> Response<bool>) -> Option<f64> {
+    let answers: Vec<bool> = group.iter().filter_map(|person| answer(person).value()).collect();
+    if answers.is_empty() {
+        return None;
+    }
+    let yes = answers.iter().filter(|&&a| a).count();
+    Some((yes * 100) as f64 / answers.len() as f64) //one rounding, 9 of 15 is exactly 60
+}
+//formats a percentage, or n/a if it could not be computed
+fn format_percent(percent: Option<f64>) -> String {
+    percent.map_or("n/a".to_string(), |p| format!("{:.1}%", p))
+}
+///share of people with high blood pressure, high cholesterol and diabetes in a group, in percent
+///each rate is over the people who answered the question and is None if nobody did
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthRates {
+    pub n: usize,
+    pub high_bp: Option<f64>,
+    pub high_cholesterol: Option<f64>,
+    pub diabetic: Option<f64>,
+}
+
+impl HealthRates {
+    /// Computes the condition rates of a group of people.
+    pub fn of(group: &[&PersonNode]) -> Self {
+        Self {
+            n: group.len(),
+            high_bp: percent_yes(group, |p| p.high_bp), //High blood pressure
+            high_cholesterol: percent_yes(group, |p| p.high_cholesterol), //High cholesterol
+            diabetic: percent_yes(group, |p| p.diabetic), //Diabetic
+        }
+    }
+    ///number of columns written by serialize_fields
+    pub const FIELDS: usize = 4;
+    /// Writes the rates as columns of a row, rows holding HealthRates stay flat since CSV has no nested columns.
+    pub fn serialize_fields<S: SerializeStruct>(&self, row: &mut S) -> Result<(), S::Error> {
+        row.serialize_field("n", &self.n)?;
+        row.serialize_field("high_bp", &self.high_bp)?;
+        row.serialize_field("high_cholesterol", &self.high_cholesterol)?;
+        row.serialize_field("diabetic", &self.diabetic)
+    }
+}
+
+impl Serialize for HealthRates {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut row = serializer.serialize_struct("HealthRates", Self::FIELDS)?;
+        self.serialize_fields(&mut row)?;
+        row.end()
+    }
+}
+
+impl fmt::Display for HealthRates {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "n = {} | High BP: {}, High Cholesterol: {}, Diabetic: {}",
+            self.n,
+            format_percent(self.high_bp),
+            format_percent(self.high_cholesterol),
+            format_percent(self.diabetic)
+        )
+    }
+}
+
+///health condition rates of the people sharing an income and food security group
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthGroup {
+    pub income: u8,
+    pub income_label: String,
+    pub food_security: u8,
+    pub food_security_label: String,
+    pub rates: HealthRates,
+}
+
+impl Serialize for HealthGroup {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut row = serializer.serialize_struct("HealthGroup", 4 + HealthRates::FIELDS)?;
+        row.serialize_field("income", &self.income)?;
+        row.serialize_field("income_label", &self.income_label)?;
+        row.serialize_field("food_security", &self.food_security)?;
+        row.serialize_field("food_security_label", &self.food_security_label)?;
+        self.rates.serialize_fields(&mut row)?;
+        row.end()
+    }
+}
+
+//computes the health conditions of people grouped by their income and food security, ordered by income then food security
+//people with a missing income or food security are left out, rates are over the people who answered
+//labels of the groups come from the code book
+pub fn health_by_income_and_food_security(people: &[PersonNode], codebook: &CodeBook) -> Vec<HealthGroup> {
+    let mut groups: BTreeMap<(u8, u8), Vec<&PersonNode>> = BTreeMap::new(); //group people by income and food security
     for person in people {
-        if person.total_income != 9 && person.food_security != 9 { //ignore invalid values
+        if let (Some(income), Some(food_security)) = (person.total_income.value(), person.food_security.value()) { //ignore missing values
             groups
-                .entry((person.total_income, person.food_security)) //create a new entry for the group
+                .entry((income, food_security)) //create a new entry for the group
                 .or_default() //if the group does not exist, create it
                 .push(person); //add the person to the group
         }
     }
+    groups.into_iter().map(|((income, food_security), group)| HealthGroup {
+        income,
+        income_label: codebook.label_or_code("Total_income", income as u32),
+        food_security,
+        food_security_label: codebook.label_or_code("Food_security", food_security as u32),
+        rates: HealthRates::of(&group),
+    }).collect()
+}
+//analyzes the health conditions of people based on their income and food security and prints the results
+pub fn analyze_health_by_income_and_food_security(people: &[PersonNode], codebook: &CodeBook) {
     println!("\n--- Health Conditions by Income and Food Security ---"); 
-    for ((income, food_security), group) in groups { //iterate over the groups
-        let count = group.len() as f32; //count the number of people in the group
-        if count == 0.0 { continue;} //skip empty groups
-        let mut high_bp = 0;
-        let mut high_cholesterol = 0;
-        let mut diabetic = 0;
-        for person in group {
-            if person.high_bp { high_bp += 1; } //High blood pressure
-            if person.high_cholesterol { high_cholesterol += 1; } //High cholesterol
-            if person.diabetic { diabetic += 1; } //Diabetic
-        }
-        println!("Income: {}, Food Security: {} | n = {} | High BP: {:.1}%, High Cholesterol: {:.1}%, Diabetic: {:.1}%", //print the results
-            income,
-            food_security,
-            count,
-            (high_bp as f32 / count) * 100.0,
-            (high_cholesterol as f32 / count) * 100.0,
-            (diabetic as f32 / count) * 100.0
-        );
-    }
-}
\ No newline at end of file
+    for group in health_by_income_and_food_security(people, codebook) { //iterate over the groups
+        println!("Income: {}, Food Security: {} | {}", group.income_label, group.food_security_label, group.rates); //print the results
+    }
+}
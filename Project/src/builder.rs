@@ -0,0 +1,128 @@
+//This module builds the similarity graphs from a list of nodes.
+//Comparing every pair is quadratic, so the default graph of people only compares the people
+//sharing the answers is_similar requires to be equal(blocking), which gives the same edges.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::hash::Hash;
+use crate::gower::GowerModel;
+use crate::graph::{ActivityLevel, GraphNode, HealthGraph, PersonNode};
+use crate::similarity::{is_similar, is_similar_with, similarity_score, similarity_score_with, SimilarityConfig};
+
+///how the graph of people is built: which rules connect two people and whether the edges are weighted by a score
+///the default options build the graph of build_graph
+#[derive(Debug, Clone, Default)]
+pub struct GraphOptions {
+    pub threshold: Option<f64>, //connect the people whose score is at least this value, weighted by the score, instead of the people passing every rule
+    pub profile: Option<SimilarityConfig>, //rules and score used instead of the default ones
+    pub gower: Option<GowerModel>, //score the people by Gower similarity instead, needs a threshold
+}
+
+/// Creates the graph of the people chosen by the options, on every core with the parallel feature:
+/// - a Gower model and a threshold: edges between the people whose Gower similarity is at least the threshold
+/// - a threshold: edges between the people whose similarity score(with the profile, if any) is at least the threshold
+/// - a profile: edges between the people the profile finds similar(see is_similar_with)
+/// - neither: the blocked graph of build_graph
+///
+/// Fails if a Gower model is given without a threshold.
+pub fn build(people: &[PersonNode], options: &GraphOptions) -> Result<HealthGraph, Box<dyn Error>> {
+    #[cfg(feature = "parallel")] //same results, on every core
+    use crate::parallel::{build_graph, build_graph_with, build_scored_graph, build_weighted_graph_with};
+    if let Some(model) = &options.gower {
+        let threshold = options.threshold.ok_or("Gower similarity needs a threshold")?;
+        return Ok(build_weighted_graph_with(people, |a, b| model.similarity(&a.record, &b.record), threshold));
+    }
+    Ok(match (options.threshold, &options.profile) {
+        (Some(threshold), Some(config)) => build_weighted_graph_with(people, |a, b| similarity_score_with(a, b, config), threshold),
+        (Some(threshold), None) => build_scored_graph(people, threshold),
+        (None, Some(config)) => build_graph_with(people, |a, b| is_similar_with(a, b, config)),
+        (None, None) => build_graph(people),
+    })
+}
+
+/// Creates a graph of the people with an edge between every two similar people(see `is_similar`).
+/// Only people with the same blocking_key are compared.
+pub fn build_graph(people: &[PersonNode]) -> HealthGraph {
+    build_graph_blocked_with(people, blocking_key, is_similar)
+}
+
+/// Creates a graph of the people with an edge between every two people whose similarity score(see `similarity_score`)
+/// is at least the threshold, weighted by that score.
+pub fn build_scored_graph(people: &[PersonNode], threshold: f64) -> HealthGraph {
+    build_weighted_graph_with(people, similarity_score, threshold)
+}
+
+/// Creates a graph of any nodes(households, regions, ...) with an edge between every two nodes the rule finds similar.
+pub fn build_graph_with<N: GraphNode + Clone>(nodes: &[N], similar: impl Fn(&N, &N) -> bool) -> HealthGraph<N> {
+    let mut graph = HealthGraph::new();
+    for node in nodes {
+        graph.add_node(node.clone());
+    }
+    //add edges to the graph based on similarity
+    let ids: Vec<_> = graph.nodes.keys().copied().collect(); //collect the ids of all nodes
+    for i in 0..ids.len() { //iterate over all nodes
+        for j in (i+1)..ids.len() { //compare each node with all other nodes
+            if similar(&graph.nodes[&ids[i]], &graph.nodes[&ids[j]]) { //check if the two nodes are similar
+                graph.add_edge(ids[i], ids[j]); //add an edge between the two nodes
+            }
+        }
+    }
+    graph
+}
+
+/// Creates a graph of any nodes with an edge between every two nodes scoring at least the threshold, weighted by their score.
+/// Scores are used as edge weights, so pairs scoring 0 or less are never connected, whatever the threshold.
+pub fn build_weighted_graph_with<N: GraphNode + Clone>(nodes: &[N], score: impl Fn(&N, &N) -> f64, threshold: f64) -> HealthGraph<N> {
+    let mut graph = HealthGraph::new();
+    for node in nodes {
+        graph.add_node(node.clone());
+    }
+    let ids: Vec<_> = graph.nodes.keys().copied().collect(); //collect the ids of all nodes
+    for i in 0..ids.len() {
+        for j in (i+1)..ids.len() {
+            let weight = score(&graph.nodes[&ids[i]], &graph.nodes[&ids[j]]);
+            if weight >= threshold && weight > 0.0 {
+                graph.add_weighted_edge(ids[i], ids[j], weight); //the score becomes the weight of the edge
+            }
+        }
+    }
+    graph
+}
+
+/// The answers is_similar requires to be equal and valid: weight state, activity level, income and food security.
+/// Returns None if one is missing, such a person is similar to nobody.
+pub fn blocking_key(person: &PersonNode) -> Option<(u8, ActivityLevel, u8, u8)> {
+    if person.activity_level == ActivityLevel::Unknown {
+        return None;
+    }
+    Some((
+        person.weight_state.value()?,
+        person.activity_level.clone(),
+        person.total_income.value()?,
+        person.food_security.value()?,
+    ))
+}
+
+/// Creates a graph of any nodes with an edge between every two nodes the rule finds similar, only comparing
+/// the nodes with the same key. Nodes without a key get no edges.
+/// The graph is the same as build_graph_with's as long as the rule never finds nodes with different keys similar.
+pub fn build_graph_blocked_with<N: GraphNode + Clone, K: Hash + Eq>(nodes: &[N], key: impl Fn(&N) -> Option<K>, similar: impl Fn(&N, &N) -> bool) -> HealthGraph<N> {
+    let mut graph = HealthGraph::new();
+    let mut blocks: HashMap<K, Vec<N::Id>> = HashMap::new(); //ids of the nodes sharing a key
+    for node in nodes {
+        if let Some(key) = key(node) {
+            blocks.entry(key).or_default().push(node.id());
+        }
+        graph.add_node(node.clone());
+    }
+    for ids in blocks.values() {
+        for i in 0..ids.len() { //compare each node with the other nodes of its block
+            for j in (i+1)..ids.len() {
+                if similar(&graph.nodes[&ids[i]], &graph.nodes[&ids[j]]) {
+                    graph.add_edge(ids[i], ids[j]);
+                }
+            }
+        }
+    }
+    graph
+}
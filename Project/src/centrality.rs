@@ -0,0 +1,192 @@
+//This module finds the important people of the graph beyond their degree.
+//Betweenness counts the shortest paths going through a node, so it finds the people whose profiles bridge clusters,
+//closeness and harmonic centrality measure how near a node is to the others, eigenvector centrality and PageRank
+//score a node by the scores of its neighbors.
+//Every measure returns a score per node, top_k sorts them.
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use crate::csr::CsrGraph;
+use crate::graph::Adjacency;
+
+//the measures freeze the graph and work on its dense indices 0..n, in the order of the ids
+
+//returns the index and weight of the neighbors of the node at an index, sorted by index so sums run in the same order
+fn weighted_at<Id: Copy + Eq + Hash + Ord>(graph: &CsrGraph<Id>, index: usize) -> impl Iterator<Item = (usize, f64)> + '_ {
+    let weights = graph.weights_at(index);
+    graph.neighbors_at(index).iter().enumerate().map(move |(i, &n)| (n as usize, weights.map_or(1.0, |weights| weights[i])))
+}
+//gives the scores of the indices back to their ids
+fn by_id<Id: Copy + Eq + Hash + Ord>(graph: &CsrGraph<Id>, scores: Vec<f64>) -> HashMap<Id, f64> {
+    scores.into_iter().enumerate().map(|(index, score)| (graph.id_at(index), score)).collect()
+}
+//computes the hop distances from a node, None for the nodes it does not reach
+fn distances<Id: Copy + Eq + Hash + Ord>(graph: &CsrGraph<Id>, start: usize) -> Vec<Option<usize>> {
+    let mut distances = vec![None; graph.len()];
+    let mut queue = VecDeque::new();
+    distances[start] = Some(0);
+    queue.push_back(start);
+    while let Some(current) = queue.pop_front() {
+        let next = distances[current].map(|distance| distance + 1);
+        for &neighbor in graph.neighbors_at(current) {
+            let neighbor = neighbor as usize;
+            if distances[neighbor].is_none() {
+                distances[neighbor] = next;
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    distances
+}
+
+/// Computes the betweenness centrality of every node with the algorithm of Brandes, O(n·m), distances count hops:
+/// the share of the shortest paths between two other nodes that go through the node, summed over the pairs.
+/// Scores are normalized by the (n-1)(n-2)/2 pairs a node can be between, so they are in [0, 1].
+pub fn betweenness<G: Adjacency>(graph: &G) -> HashMap<G::Id, f64> {
+    let csr = CsrGraph::from_adjacency(graph);
+    let n = csr.len();
+    let mut centrality = vec![0.0; n];
+    for source in 0..n {
+        //count the shortest paths from the source with a BFS, keeping the nodes in the order they are reached
+        let mut order = Vec::with_capacity(n);
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+        let mut paths = vec![0.0; n];
+        let mut distance: Vec<Option<usize>> = vec![None; n];
+        paths[source] = 1.0;
+        distance[source] = Some(0);
+        let mut queue = VecDeque::from([source]);
+        while let Some(current) = queue.pop_front() {
+            order.push(current);
+            let next = distance[current].map(|d| d + 1);
+            for &neighbor in csr.neighbors_at(current) {
+                let neighbor = neighbor as usize;
+                if distance[neighbor].is_none() {
+                    distance[neighbor] = next;
+                    queue.push_back(neighbor);
+                }
+                if distance[neighbor] == next { //a shortest path to the neighbor goes through current
+                    paths[neighbor] += paths[current];
+                    predecessors[neighbor].push(current);
+                }
+            }
+        }
+        //accumulate the dependencies from the farthest nodes back to the source
+        let mut dependency = vec![0.0; n];
+        for &node in order.iter().rev() {
+            for &predecessor in &predecessors[node] {
+                dependency[predecessor] += paths[predecessor] / paths[node] * (1.0 + dependency[node]);
+            }
+            if node != source {
+                centrality[node] += dependency[node];
+            }
+        }
+    }
+    //every pair was counted from both of its ends
+    let scale = if n > 2 { 1.0 / ((n - 1) * (n - 2)) as f64 } else { 0.0 };
+    by_id(&csr, centrality.into_iter().map(|score| score * scale).collect())
+}
+
+/// Computes the closeness centrality of every node, the inverse of its mean distance to the nodes it reaches.
+/// In a graph with several components the score is scaled by the share of the other nodes reached(Wasserman and Faust),
+/// so a node of a small component does not look central. Isolated nodes get 0.
+pub fn closeness<G: Adjacency>(graph: &G) -> HashMap<G::Id, f64> {
+    let csr = CsrGraph::from_adjacency(graph);
+    let n = csr.len();
+    let scores = (0..n)
+        .map(|node| {
+            let reached: Vec<usize> = distances(&csr, node).into_iter().flatten().filter(|&d| d > 0).collect();
+            let total: usize = reached.iter().sum();
+            if total == 0 {
+                return 0.0;
+            }
+            let count = reached.len() as f64;
+            (count / total as f64) * (count / (n - 1) as f64)
+        })
+        .collect();
+    by_id(&csr, scores)
+}
+
+/// Computes the harmonic centrality of every node, the sum of the inverse distances to the other nodes
+/// divided by n-1, in [0, 1]. Unreachable nodes add 0, so it needs no correction for disconnected graphs.
+pub fn harmonic<G: Adjacency>(graph: &G) -> HashMap<G::Id, f64> {
+    let csr = CsrGraph::from_adjacency(graph);
+    let n = csr.len();
+    let scores = (0..n)
+        .map(|node| {
+            let total: f64 = distances(&csr, node).into_iter().flatten().filter(|&d| d > 0).map(|d| 1.0 / d as f64).sum();
+            if n > 1 { total / (n - 1) as f64 } else { 0.0 }
+        })
+        .collect();
+    by_id(&csr, scores)
+}
+
+///damping factor of PageRank in most of the literature
+pub const DEFAULT_DAMPING: f64 = 0.85;
+
+//iterations and tolerance of the power iterations
+const MAX_ITERATIONS: usize = 1000;
+const TOLERANCE: f64 = 1e-10;
+
+/// Computes the eigenvector centrality of every node by power iteration over the edge weights:
+/// a node scores high if its neighbors score high. The scores have a Euclidean norm of 1.
+/// The iteration runs on A + I, which has the same leading eigenvector as A and also converges on bipartite graphs.
+pub fn eigenvector<G: Adjacency>(graph: &G) -> HashMap<G::Id, f64> {
+    let csr = CsrGraph::from_adjacency(graph);
+    let n = csr.len();
+    if n == 0 {
+        return HashMap::new();
+    }
+    let mut scores = vec![1.0 / (n as f64).sqrt(); n];
+    for _ in 0..MAX_ITERATIONS {
+        let mut next: Vec<f64> = (0..n)
+            .map(|node| scores[node] + weighted_at(&csr, node).map(|(neighbor, weight)| weight * scores[neighbor]).sum::<f64>())
+            .collect();
+        let norm = next.iter().map(|score| score * score).sum::<f64>().sqrt();
+        next.iter_mut().for_each(|score| *score /= norm);
+        let change: f64 = next.iter().zip(&scores).map(|(a, b)| (a - b).abs()).sum();
+        scores = next;
+        if change < TOLERANCE * n as f64 {
+            break;
+        }
+    }
+    by_id(&csr, scores)
+}
+
+/// Computes the PageRank of every node: a random walk follows an edge with a probability proportional
+/// to its weight, or jumps to any node with probability 1 - damping(usually DEFAULT_DAMPING).
+/// Isolated nodes jump to any node. The scores add up to 1.
+pub fn pagerank<G: Adjacency>(graph: &G, damping: f64) -> HashMap<G::Id, f64> {
+    let csr = CsrGraph::from_adjacency(graph);
+    let n = csr.len();
+    if n == 0 {
+        return HashMap::new();
+    }
+    let strengths: Vec<f64> = (0..n).map(|node| weighted_at(&csr, node).map(|(_, weight)| weight).sum()).collect();
+    let mut scores = vec![1.0 / n as f64; n];
+    for _ in 0..MAX_ITERATIONS {
+        //the rank of the nodes without edges is shared by every node
+        let dangling: f64 = (0..n).filter(|&node| strengths[node] == 0.0).map(|node| scores[node]).sum();
+        let base = (1.0 - damping) / n as f64 + damping * dangling / n as f64;
+        let next: Vec<f64> = (0..n)
+            .map(|node| {
+                let received: f64 = weighted_at(&csr, node).map(|(neighbor, weight)| scores[neighbor] * weight / strengths[neighbor]).sum();
+                base + damping * received
+            })
+            .collect();
+        let change: f64 = next.iter().zip(&scores).map(|(a, b)| (a - b).abs()).sum();
+        scores = next;
+        if change < TOLERANCE {
+            break;
+        }
+    }
+    by_id(&csr, scores)
+}
+
+/// Returns the k nodes with the highest scores, highest first, ties go to the smallest id.
+pub fn top_k<Id: Copy + Ord>(scores: &HashMap<Id, f64>, k: usize) -> Vec<(Id, f64)> {
+    let mut ranked: Vec<(Id, f64)> = scores.iter().map(|(&id, &score)| (id, score)).collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(k);
+    ranked
+}
+
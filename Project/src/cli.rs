@@ -0,0 +1,243 @@
+//This module defines the command-line interface of the program and how its results are printed
+
+use std::error::Error;
+use std::io;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use health_graph::parser::ParsePolicy;
+use health_graph::sampling::{self, SampleDesign};
+
+//defaults of the input and sample size options
+pub const DEFAULT_INPUT: &str = "health_dataset.csv";
+pub const DEFAULT_SAMPLE_SIZE: usize = 10000;
+
+///Builds a similarity graph of CCHS respondents and analyzes their health
+#[derive(Debug, Parser)]
+#[command(name = "Project", version, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>, //without a subcommand every analysis is run, like before the CLI existed
+    #[command(flatten)]
+    pub graph: GraphArgs, //options of the run without a subcommand, so `Project --seed 42` reproduces it
+    ///format of the printed results
+    #[arg(long, value_enum, default_value_t = Format::Text, global = true)]
+    pub format: Format,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    ///Load and validate the dataset and report every rejected cell
+    #[command(alias = "load")]
+    Validate(InputArgs),
+    ///Build the similarity graph and print its size
+    BuildGraph(GraphArgs),
+    ///Build the similarity graph and print its degree and path statistics
+    Stats(GraphArgs),
+    ///Print the health conditions by income and food security
+    Crosstab(InputArgs),
+    ///Build the similarity graph and write its nodes or edges
+    Export(ExportArgs),
+    ///Build the similarity graph and print the most central people
+    Centrality(CentralityArgs),
+    ///Build the similarity graph, find its communities and print their health conditions
+    Communities(CommunityArgs),
+}
+
+///options shared by every command that loads the dataset
+#[derive(Debug, Clone, Args)]
+pub struct InputArgs {
+    ///path of the CSV dataset
+    #[arg(short, long, default_value = DEFAULT_INPUT)]
+    pub input: String,
+    ///code book file(TOML or JSON) used instead of the bundled one
+    #[arg(long)]
+    pub codebook: Option<String>,
+    ///what to do with cells that fail validation
+    #[arg(long, value_enum, default_value_t = Policy::Coerce)]
+    pub policy: Policy,
+}
+
+///options of the commands that build the graph
+#[derive(Debug, Clone, Args)]
+pub struct GraphArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+    ///number of people sampled for the graph
+    #[arg(short = 'n', long, default_value_t = DEFAULT_SAMPLE_SIZE)]
+    pub sample_size: usize,
+    ///seed of the sampling, a random seed is drawn and printed if not given
+    #[arg(long)]
+    pub seed: Option<u64>,
+    ///categorical column the sample is stratified by(Province, Total_income, Age, ...)
+    #[arg(long)]
+    pub stratify: Option<String>,
+    ///how the sample is shared between the strata
+    #[arg(long, value_enum, default_value_t = Allocation::Proportional)]
+    pub allocation: Allocation,
+    ///column of the survey weights, people are then drawn with a probability proportional to their weight
+    #[arg(long)]
+    pub weights: Option<String>,
+    ///connect people whose similarity score(0 to 1) is at least this value(above 0, at most 1),
+    ///weighting the edges by the score, instead of connecting the people who pass every similarity rule
+    #[arg(long, value_parser = parse_threshold)]
+    pub threshold: Option<f64>,
+    ///similarity profile(TOML) used instead of the default rules, see similarity.toml
+    #[arg(long)]
+    pub profile: Option<String>,
+    ///score people by their Gower similarity over every column instead, needs a threshold
+    #[arg(long, requires = "threshold", conflicts_with = "profile")]
+    pub gower: bool,
+    ///estimate the average shortest path length from this many sampled source nodes instead of every node,
+    ///with a 95% confidence interval, at least 2
+    #[arg(long, value_parser = parse_path_sources)]
+    pub path_sources: Option<usize>,
+}
+
+//parses a similarity threshold, a threshold of 0 would connect every pair with an edge of weight 0
+fn parse_threshold(text: &str) -> Result<f64, String> {
+    let value: f64 = text.parse().map_err(|_| format!("{:?} is not a number", text))?;
+    if value > 0.0 && value <= 1.0 {
+        Ok(value)
+    } else {
+        Err(format!("{} is not in (0, 1]", value))
+    }
+}
+
+//parses a number of path sources, a single source cannot measure the spread of the estimate
+fn parse_path_sources(text: &str) -> Result<usize, String> {
+    let value: usize = text.parse().map_err(|_| format!("{:?} is not a number of nodes", text))?;
+    if value >= 2 {
+        Ok(value)
+    } else {
+        Err(format!("{} sources give no confidence interval, use at least 2", value))
+    }
+}
+
+impl GraphArgs {
+    /// Returns the sample design chosen by the options.
+    pub fn design(&self) -> SampleDesign {
+        SampleDesign {
+            stratify_by: self.stratify.clone(),
+            allocation: self.allocation.into(),
+            weighted: self.weights.is_some(),
+        }
+    }
+}
+
+///options of the export command
+#[derive(Debug, Clone, Args)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    pub graph: GraphArgs,
+    ///what to export
+    #[arg(long, value_enum, default_value_t = ExportKind::Edges)]
+    pub what: ExportKind,
+    ///file to write, standard output if not given
+    #[arg(short, long)]
+    pub output: Option<String>,
+}
+
+///options of the centrality command
+#[derive(Debug, Clone, Args)]
+pub struct CentralityArgs {
+    #[command(flatten)]
+    pub graph: GraphArgs,
+    ///centrality measure to rank the people by
+    #[arg(long, value_enum, default_value_t = Measure::Betweenness)]
+    pub measure: Measure,
+    ///number of people printed
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+}
+
+///options of the communities command
+#[derive(Debug, Clone, Args)]
+pub struct CommunityArgs {
+    #[command(flatten)]
+    pub graph: GraphArgs,
+    ///community detection algorithm, label propagation draws its node order from the sample seed
+    #[arg(long, value_enum, default_value_t = Method::Louvain)]
+    pub method: Method,
+    ///number of communities printed, largest first
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Policy {
+    Fail,
+    Skip,
+    Coerce,
+}
+
+impl From<Policy> for ParsePolicy {
+    fn from(policy: Policy) -> Self {
+        match policy {
+            Policy::Fail => ParsePolicy::FailFast,
+            Policy::Skip => ParsePolicy::SkipRow,
+            Policy::Coerce => ParsePolicy::CoerceToMissing,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Allocation {
+    Proportional,
+    Equal,
+}
+
+impl From<Allocation> for sampling::Allocation {
+    fn from(allocation: Allocation) -> Self {
+        match allocation {
+            Allocation::Proportional => sampling::Allocation::Proportional,
+            Allocation::Equal => sampling::Allocation::Equal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportKind {
+    Nodes,
+    Edges,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Measure {
+    Betweenness,
+    Closeness,
+    Harmonic,
+    Eigenvector,
+    Pagerank,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Method {
+    Louvain,
+    LabelPropagation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Text,
+    Csv,
+    Json,
+}
+
+/// Writes rows as CSV(one line per row) or JSON(an array) to a writer.
+/// Text output is printed by each command itself, so it is written as CSV here.
+pub fn write_rows<T: Serialize>(rows: &[T], format: Format, mut writer: impl io::Write) -> Result<(), Box<dyn Error>> {
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut writer, rows)?;
+            writeln!(writer)?;
+        }
+        Format::Text | Format::Csv => {
+            let mut csv_writer = csv::Writer::from_writer(writer);
+            for row in rows {
+                csv_writer.serialize(row)?;
+            }
+            csv_writer.flush()?;
+        }
+    }
+    Ok(())
+}
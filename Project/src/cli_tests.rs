@@ -0,0 +1,48 @@
+//This contains the tests of the command-line interface, the library is tested in tests.rs
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests {
+    use super::super::cli::{Cli, Command, Format, Measure, Method, Policy};
+    use clap::Parser;
+
+    //Tests parsing the command line
+    #[test]
+    fn test_cli_arguments() {
+        let cli = Cli::try_parse_from(["Project", "stats", "--input", "data.csv", "-n", "500", "--format", "json"]).unwrap();
+        assert_eq!(cli.format, Format::Json);
+        match cli.command {
+            Some(Command::Stats(args)) => {
+                assert_eq!(args.input.input, "data.csv");
+                assert_eq!(args.sample_size, 500);
+                assert_eq!(args.input.policy, Policy::Coerce);
+            }
+            _ => panic!("expected the stats command"),
+        }
+        let cli = Cli::try_parse_from(["Project", "load", "--policy", "fail"]).unwrap(); //load is an alias of validate
+        assert!(matches!(cli.command, Some(Command::Validate(args)) if args.policy == Policy::Fail));
+        assert!(Cli::try_parse_from(["Project"]).unwrap().command.is_none());
+        //without a subcommand the graph options are still taken, so the full run can be reproduced
+        let cli = Cli::try_parse_from(["Project", "--seed", "42", "-n", "500"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!((cli.graph.seed, cli.graph.sample_size), (Some(42), 500));
+        assert!(Cli::try_parse_from(["Project", "--seed", "42", "stats"]).is_err());
+        assert!(Cli::try_parse_from(["Project", "stats", "--sample-size", "many"]).is_err());
+        let cli = Cli::try_parse_from(["Project", "export", "--seed", "42"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Export(args)) if args.graph.seed == Some(42)));
+        let cli = Cli::try_parse_from(["Project", "stats", "--path-sources", "200"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Stats(args)) if args.path_sources == Some(200)));
+        for sources in ["0", "1", "-2"] {
+            assert!(Cli::try_parse_from(["Project", "stats", "--path-sources", sources]).is_err());
+        }
+        let cli = Cli::try_parse_from(["Project", "build-graph", "--threshold", "1"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::BuildGraph(args)) if args.threshold == Some(1.0)));
+        for threshold in ["0", "-0.5", "1.5", "NaN"] {
+            assert!(Cli::try_parse_from(["Project", "build-graph", "--threshold", threshold]).is_err());
+        }
+        let cli = Cli::try_parse_from(["Project", "centrality", "--measure", "pagerank", "--top", "5"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Centrality(args)) if args.measure == Measure::Pagerank && args.top == 5));
+        let cli = Cli::try_parse_from(["Project", "communities", "--method", "label-propagation"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Communities(args)) if args.method == Method::LabelPropagation && args.top == 10));
+    }
+}
@@ -0,0 +1,141 @@
+//This module defines the code book that decodes the categorical CCHS codes into labels
+//and drives validation and labelling at runtime.
+//The bundled codes and labels follow Projectdata/Health_Dataset_Decoding_and_mapping_dictionary.docx.
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use serde::Deserialize;
+use crate::schema::{Column, ColumnKind, Missing, Response};
+
+//a column of a code book file, before its codes are parsed
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColumnFile {
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    ordinal: bool,
+    range: Option<(u32, u32)>,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+    #[serde(default)]
+    missing: BTreeMap<String, Missing>,
+}
+
+///the codes of one column: labels of the valid codes, the valid range of numeric values,
+///the reserve codes and whether the codes are ordered
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnCodes {
+    pub description: String,
+    pub ordinal: bool,
+    pub range: Option<(u32, u32)>,
+    pub labels: BTreeMap<u32, String>,
+    pub missing: BTreeMap<u32, Missing>,
+}
+
+impl ColumnCodes {
+    /// Returns true if the value is a labelled code, inside the valid range or a reserve code.
+    /// A column with neither labels nor a range accepts every value.
+    pub fn accepts(&self, value: u32) -> bool {
+        if self.labels.is_empty() && self.range.is_none() {
+            return true;
+        }
+        self.labels.contains_key(&value)
+            || self.range.is_some_and(|(low, high)| (low..=high).contains(&value))
+            || self.missing.contains_key(&value)
+    }
+}
+
+///code book of the dataset, maps a column name to its codes
+///it drives the validation of the parser and the labels printed by the analysis
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CodeBook {
+    pub columns: HashMap<String, ColumnCodes>,
+}
+
+//the default code book, generated from the data dictionaries
+const BUNDLED: &str = include_str!("../codebook.toml");
+
+impl CodeBook {
+    /// Returns the code book bundled with the program(codebook.toml).
+    pub fn bundled() -> Self {
+        Self::from_toml(BUNDLED).expect("the bundled code book is valid")
+    }
+    /// Loads a code book from a JSON file(.json) or a TOML file(any other extension).
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        if path.ends_with(".json") {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        }
+    }
+    /// Parses a code book from TOML text, one table per column.
+    pub fn from_toml(text: &str) -> Result<Self, Box<dyn Error>> {
+        Self::from_columns(toml::from_str(text)?)
+    }
+    /// Parses a code book from JSON text, one object per column.
+    pub fn from_json(text: &str) -> Result<Self, Box<dyn Error>> {
+        Self::from_columns(serde_json::from_str(text)?)
+    }
+    //converts the columns of a file, parsing the code keys into numbers
+    fn from_columns(files: BTreeMap<String, ColumnFile>) -> Result<Self, Box<dyn Error>> {
+        let code = |column: &str, key: &str| -> Result<u32, Box<dyn Error>> {
+            key.trim().parse().map_err(|_| format!("column {}: code {:?} is not a number", column, key).into())
+        };
+        let mut columns = HashMap::new();
+        for (name, file) in files {
+            let mut codes = ColumnCodes { description: file.description, ordinal: file.ordinal, range: file.range, ..Default::default() };
+            for (key, label) in file.labels {
+                codes.labels.insert(code(&name, &key)?, label);
+            }
+            for (key, reason) in file.missing {
+                codes.missing.insert(code(&name, &key)?, reason);
+            }
+            columns.insert(name, codes);
+        }
+        Ok(Self { columns })
+    }
+    /// Returns the codes of a column, None if the column is not in the code book.
+    pub fn column(&self, name: &str) -> Option<&ColumnCodes> {
+        self.columns.get(name)
+    }
+    /// Returns the label of a valid code, None if the column or code has no label.
+    pub fn label(&self, column: &str, code: u32) -> Option<&str> {
+        self.column(column)?.labels.get(&code).map(|label| label.as_str())
+    }
+    /// Returns the label of a code, or the code itself if it has no label.
+    pub fn label_or_code(&self, column: &str, code: u32) -> String {
+        self.label(column, code).map_or(code.to_string(), |label| label.to_string())
+    }
+    /// Returns true if the code book accepts the value for the column, columns it does not know accept everything.
+    pub fn accepts(&self, column: &str, value: u32) -> bool {
+        self.column(column).is_none_or(|codes| codes.accepts(value))
+    }
+    /// Returns true if the codes of the column are ordered.
+    pub fn is_ordinal(&self, column: &str) -> bool {
+        self.column(column).is_some_and(|codes| codes.ordinal)
+    }
+    /// Returns the kind of a column: the ordinal flag of the code book decides whether the codes of a
+    /// nominal or ordinal column are ordered, the schema gives the kind of every other column
+    /// and of the columns the code book does not list.
+    pub fn kind(&self, column: &Column) -> ColumnKind {
+        match (column.kind, self.column(column.name)) {
+            (ColumnKind::Nominal | ColumnKind::Ordinal, Some(codes)) if codes.ordinal => ColumnKind::Ordinal,
+            (ColumnKind::Nominal | ColumnKind::Ordinal, Some(_)) => ColumnKind::Nominal,
+            (kind, _) => kind,
+        }
+    }
+    /// Parses a raw cell into a Response, turning the column's reserve codes into their Missing reason.
+    /// The value is not checked against the valid codes, see `accepts`.
+    pub fn response(&self, column: &str, raw: &str) -> Response<u32> {
+        match raw.trim().parse::<u32>() {
+            Ok(value) => match self.column(column).and_then(|codes| codes.missing.get(&value)) {
+                Some(&reason) => Response::Missing(reason),
+                None => Response::Valid(value),
+            },
+            Err(_) => Response::Missing(Missing::Blank),
+        }
+    }
+}
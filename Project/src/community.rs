@@ -0,0 +1,272 @@
+//This module finds the health-profile clusters of the graph, groups of people more connected to each other than to the rest.
+//Louvain greedily moves nodes between communities to raise the modularity and then merges each community into one node,
+//label propagation lets every node take the label most of its neighbors have until the labels settle.
+//The health of each community is summarized with the same rates as the income and food security groups.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Serialize;
+use serde::ser::{SerializeStruct, Serializer};
+use crate::analysis::{largest_first, HealthRates};
+use crate::graph::{Adjacency, HealthGraph, PersonNode};
+
+///communities of a graph, numbered from the largest(ties go to the community with the smallest id)
+#[derive(Debug, Clone)]
+pub struct Communities<Id> {
+    pub community_of: HashMap<Id, usize>, //community number of each node
+    pub sizes: Vec<usize>, //number of nodes of each community, largest first
+    pub modularity: f64,
+}
+
+impl<Id: Copy + Ord + Hash> Communities<Id> {
+    /// Returns the number of communities, isolated nodes are communities of their own.
+    pub fn count(&self) -> usize {
+        self.sizes.len()
+    }
+    /// Returns the community number of a node, None if it is not in the graph.
+    pub fn community(&self, id: Id) -> Option<usize> {
+        self.community_of.get(&id).copied()
+    }
+    /// Returns the nodes of a community, sorted.
+    pub fn members(&self, community: usize) -> Vec<Id> {
+        let mut members: Vec<Id> = self.community_of.iter().filter(|&(_, &c)| c == community).map(|(&id, _)| id).collect();
+        members.sort();
+        members
+    }
+    //numbers the labels of the nodes(given in the order of the ids) from the largest community and computes the modularity
+    fn numbered<G: Adjacency<Id = Id>>(graph: &G, ids: &[Id], labels: &[usize]) -> Self {
+        let mut first: HashMap<usize, usize> = HashMap::new(); //label -> number in the order of the smallest id
+        for &label in labels {
+            let next = first.len();
+            first.entry(label).or_insert(next);
+        }
+        let mut sizes = vec![0; first.len()];
+        for label in labels {
+            sizes[first[label]] += 1;
+        }
+        let (rank, sizes) = largest_first(&sizes);
+        let community_of: HashMap<Id, usize> = ids.iter().zip(labels).map(|(&id, label)| (id, rank[first[label]])).collect();
+        Self {
+            modularity: modularity(graph, &community_of),
+            sizes,
+            community_of,
+        }
+    }
+}
+
+/// Computes the modularity of a partition of the graph over the edge weights, in [-1/2, 1]:
+/// the share of the weight inside the communities minus the share expected if the edges were placed at random.
+/// Nodes missing from the partition are communities of their own, a graph without edges has a modularity of 0.
+pub fn modularity<G: Adjacency>(graph: &G, community_of: &HashMap<G::Id, usize>) -> f64 {
+    let mut ids: Vec<G::Id> = graph.node_ids().collect();
+    ids.sort(); //sum in the same order on every run
+    let total: f64 = ids.iter().map(|&id| graph.strength(id)).sum(); //twice the weight of the edges
+    if total == 0.0 {
+        return 0.0;
+    }
+    let mut inside = 0.0; //weight of the edges inside the communities, counted from both ends
+    let mut strengths: HashMap<usize, f64> = HashMap::new(); //strength of each community
+    let mut alone = Vec::new(); //strength of the nodes missing from the partition
+    for &id in &ids {
+        let Some(&community) = community_of.get(&id) else {
+            alone.push(graph.strength(id));
+            continue;
+        };
+        inside += graph.weighted_neighbors(id).filter(|(n, _)| community_of.get(n) == Some(&community)).map(|(_, weight)| weight).sum::<f64>();
+        *strengths.entry(community).or_insert(0.0) += graph.strength(id);
+    }
+    let mut expected: Vec<f64> = strengths.into_values().chain(alone).collect();
+    expected.sort_by(f64::total_cmp);
+    inside / total - expected.iter().map(|strength| (strength / total).powi(2)).sum::<f64>()
+}
+
+//a weighted graph on indices where a node can have a loop, the form Louvain merges communities into
+struct Level {
+    neighbors: Vec<Vec<(usize, f64)>>, //A_ij of every j with a nonzero entry, j == i for the weight inside a merged node
+}
+
+impl Level {
+    fn strength(&self, node: usize) -> f64 {
+        self.neighbors[node].iter().map(|&(_, weight)| weight).sum()
+    }
+    //moves every node to the neighboring community that raises the modularity most until no move helps
+    //returns the community of every node, numbered 0.., and whether any node moved
+    fn move_nodes(&self) -> (Vec<usize>, bool) {
+        let n = self.neighbors.len();
+        let strengths: Vec<f64> = (0..n).map(|node| self.strength(node)).collect();
+        let total: f64 = strengths.iter().sum();
+        let mut community: Vec<usize> = (0..n).collect();
+        let mut totals = strengths.clone(); //strength of each community
+        let mut moved = false;
+        loop {
+            let mut changed = false;
+            for node in 0..n {
+                let current = community[node];
+                totals[current] -= strengths[node];
+                //weight from the node to each neighboring community, its own community first
+                let mut links: Vec<(usize, f64)> = vec![(current, 0.0)];
+                for &(neighbor, weight) in &self.neighbors[node] {
+                    if neighbor == node {
+                        continue;
+                    }
+                    match links.iter_mut().find(|(c, _)| *c == community[neighbor]) {
+                        Some(link) => link.1 += weight,
+                        None => links.push((community[neighbor], weight)),
+                    }
+                }
+                //the gain of joining a community, up to a constant factor
+                let gain = |&(c, weight): &(usize, f64)| weight - totals[c] * strengths[node] / total;
+                //a move must raise the modularity by more than the rounding, equal moves go to the smallest community
+                let mut best = links[0];
+                for link in &links[1..] {
+                    let better = gain(link) - gain(&best);
+                    if better > 1e-12 || (better.abs() <= 1e-12 && best.0 != current && link.0 < best.0) {
+                        best = *link;
+                    }
+                }
+                community[node] = best.0;
+                totals[best.0] += strengths[node];
+                if best.0 != current {
+                    changed = true;
+                    moved = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        //number the communities 0.. in the order of their first node
+        let mut numbers: HashMap<usize, usize> = HashMap::new();
+        let community = community.into_iter()
+            .map(|c| {
+                let next = numbers.len();
+                *numbers.entry(c).or_insert(next)
+            })
+            .collect();
+        (community, moved)
+    }
+    //merges the nodes of each community into one node, the weights between and inside communities are summed
+    fn merge(&self, community: &[usize]) -> Level {
+        let count = community.iter().max().map_or(0, |&c| c + 1);
+        let mut weights: Vec<HashMap<usize, f64>> = vec![HashMap::new(); count];
+        for (node, neighbors) in self.neighbors.iter().enumerate() {
+            for &(neighbor, weight) in neighbors {
+                *weights[community[node]].entry(community[neighbor]).or_insert(0.0) += weight;
+            }
+        }
+        let neighbors = weights.into_iter()
+            .map(|weights| {
+                let mut neighbors: Vec<(usize, f64)> = weights.into_iter().collect();
+                neighbors.sort_by_key(|&(neighbor, _)| neighbor); //sum in the same order on every run
+                neighbors
+            })
+            .collect();
+        Level { neighbors }
+    }
+}
+
+/// Finds communities with the Louvain method over the edge weights: nodes are moved to the neighboring community
+/// that raises the modularity most, then every community is merged into one node and the moves start again,
+/// until no move raises the modularity. Nodes are visited in the order of their ids, so the result is the same on every run.
+pub fn louvain<G: Adjacency>(graph: &G) -> Communities<G::Id> {
+    let mut ids: Vec<G::Id> = graph.node_ids().collect();
+    ids.sort();
+    let index: HashMap<G::Id, usize> = ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let mut level = Level {
+        neighbors: ids.iter()
+            .map(|&id| {
+                let mut neighbors: Vec<(usize, f64)> = graph.weighted_neighbors(id).map(|(n, weight)| (index[&n], weight)).collect();
+                neighbors.sort_by_key(|&(n, _)| n);
+                neighbors
+            })
+            .collect(),
+    };
+    let mut labels: Vec<usize> = (0..ids.len()).collect(); //community of every node of the graph
+    if ids.iter().all(|&id| graph.degree(id) == 0) {
+        return Communities::numbered(graph, &ids, &labels); //no edge to gain modularity from
+    }
+    loop {
+        let (community, moved) = level.move_nodes();
+        if !moved {
+            break;
+        }
+        labels.iter_mut().for_each(|label| *label = community[*label]);
+        level = level.merge(&community);
+    }
+    Communities::numbered(graph, &ids, &labels)
+}
+
+//rounds of label propagation before giving up on settling
+const MAX_ROUNDS: usize = 100;
+
+/// Finds communities by label propagation: every node starts with its own label and, in a random order each round,
+/// takes the label with the largest edge weight among its neighbors(ties go to the smallest label)
+/// until every node has such a label. Faster than Louvain but the result depends on the RNG;
+/// the same graph and RNG state always give the same communities.
+pub fn label_propagation<G: Adjacency>(graph: &G, rng: &mut impl Rng) -> Communities<G::Id> {
+    let mut ids: Vec<G::Id> = graph.node_ids().collect();
+    ids.sort(); //shuffle from the same order on every run
+    let index: HashMap<G::Id, usize> = ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let mut labels: Vec<usize> = (0..ids.len()).collect();
+    let mut order: Vec<usize> = (0..ids.len()).collect();
+    //the label of a node with the most weight among its neighbors, and whether its current label is one of the best
+    let best_label = |labels: &[usize], node: usize| -> Option<(usize, bool)> {
+        let mut weights: HashMap<usize, f64> = HashMap::new();
+        for (neighbor, weight) in graph.weighted_neighbors(ids[node]) {
+            *weights.entry(labels[index[&neighbor]]).or_insert(0.0) += weight;
+        }
+        let top = weights.values().copied().fold(f64::NEG_INFINITY, f64::max);
+        let best = weights.iter().filter(|&(_, &w)| w >= top - 1e-12).map(|(&label, _)| label);
+        let current = labels[node];
+        let mut best: Vec<usize> = best.collect();
+        best.sort();
+        best.first().map(|&label| (label, best.contains(&current)))
+    };
+    for _ in 0..MAX_ROUNDS {
+        order.shuffle(rng);
+        for &node in &order {
+            if let Some((label, keep)) = best_label(&labels, node) {
+                if !keep {
+                    labels[node] = label;
+                }
+            }
+        }
+        //stop when every node already has one of the best labels of its neighbors
+        if (0..ids.len()).all(|node| best_label(&labels, node).is_none_or(|(_, keep)| keep)) {
+            break;
+        }
+    }
+    Communities::numbered(graph, &ids, &labels)
+}
+
+///health condition rates of the people of a community
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommunityHealth {
+    pub community: usize,
+    pub rates: HealthRates,
+}
+
+impl Serialize for CommunityHealth {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut row = serializer.serialize_struct("CommunityHealth", 1 + HealthRates::FIELDS)?;
+        row.serialize_field("community", &self.community)?;
+        self.rates.serialize_fields(&mut row)?;
+        row.end()
+    }
+}
+
+//computes the health conditions of the people of each community, in the order of the communities
+//rates are over the people who answered, like the income and food security groups
+pub fn health_by_community(graph: &HealthGraph, communities: &Communities<usize>) -> Vec<CommunityHealth> {
+    let mut groups: Vec<Vec<&PersonNode>> = vec![Vec::new(); communities.count()];
+    let mut ids: Vec<usize> = graph.nodes.keys().copied().collect();
+    ids.sort();
+    for id in ids {
+        if let Some(community) = communities.community(id) {
+            groups[community].push(&graph.nodes[&id]);
+        }
+    }
+    groups.into_iter().enumerate().map(|(community, group)| CommunityHealth { community, rates: HealthRates::of(&group) }).collect()
+}
@@ -0,0 +1,105 @@
+//This module defines the compact, read-only form of a graph for large analyses.
+//A HashMap of HashSets costs tens of bytes per edge, a compressed sparse row(CSR) layout
+//stores the neighbors of every node in one contiguous array, 4 bytes per edge end(plus 8 for the weight of a weighted graph).
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use crate::graph::{Adjacency, GraphNode, HealthGraph};
+
+///frozen graph in compressed sparse row form, built from a HealthGraph
+///nodes get dense indices 0..n in the order of their ids, the neighbors of node i are
+///`targets[offsets[i]..offsets[i + 1]]`, sorted by index
+#[derive(Debug, Clone)]
+pub struct CsrGraph<Id = usize> {
+    ids: Vec<Id>, //index -> id, sorted
+    index: HashMap<Id, u32>, //id -> index
+    offsets: Vec<usize>, //start of the neighbors of each node in targets, one more entry than nodes
+    targets: Vec<u32>, //indices of the neighbors of every node, one after the other
+    weights: Vec<f64>, //weights matching targets, empty if every edge weighs 1.0
+}
+
+impl<Id: Copy + Eq + Hash + Ord> CsrGraph<Id> {
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+    /// Returns true if the graph has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+    /// Returns the dense index of a node, None if the id is not in the graph.
+    pub fn index_of(&self, id: Id) -> Option<usize> {
+        self.index.get(&id).map(|&index| index as usize)
+    }
+    /// Returns the id of the node at an index.
+    pub fn id_at(&self, index: usize) -> Id {
+        self.ids[index]
+    }
+    /// Returns the indices of the neighbors of the node at an index.
+    pub fn neighbors_at(&self, index: usize) -> &[u32] {
+        &self.targets[self.offsets[index]..self.offsets[index + 1]]
+    }
+    /// Returns the weights of the edges to the neighbors of the node at an index, in the order of neighbors_at.
+    /// Returns None if the graph is unweighted(every edge weighs 1.0).
+    pub fn weights_at(&self, index: usize) -> Option<&[f64]> {
+        (!self.weights.is_empty()).then(|| &self.weights[self.offsets[index]..self.offsets[index + 1]])
+    }
+    /// Returns true if the edges have weights other than 1.0.
+    pub fn is_weighted(&self) -> bool {
+        !self.weights.is_empty()
+    }
+    /// Freezes any graph, a HealthGraph or another CsrGraph.
+    /// Panics if the graph has more than u32::MAX nodes.
+    pub fn from_adjacency<G: Adjacency<Id = Id>>(graph: &G) -> Self {
+        let mut ids: Vec<Id> = graph.node_ids().collect();
+        ids.sort();
+        assert!(ids.len() <= u32::MAX as usize, "too many nodes for a CsrGraph");
+        let index: HashMap<Id, u32> = ids.iter().enumerate().map(|(i, &id)| (id, i as u32)).collect();
+        let weighted = ids.iter().any(|&id| graph.weighted_neighbors(id).any(|(_, weight)| weight != 1.0));
+        let mut offsets = Vec::with_capacity(ids.len() + 1);
+        let mut targets = Vec::with_capacity(graph.edge_count() * 2);
+        let mut weights = Vec::new();
+        offsets.push(0);
+        for &id in &ids {
+            let mut neighbors: Vec<(u32, f64)> = graph.weighted_neighbors(id).map(|(neighbor, weight)| (index[&neighbor], weight)).collect();
+            neighbors.sort_by_key(|&(neighbor, _)| neighbor);
+            if weighted {
+                weights.extend(neighbors.iter().map(|&(_, weight)| weight));
+            }
+            targets.extend(neighbors.into_iter().map(|(neighbor, _)| neighbor));
+            offsets.push(targets.len());
+        }
+        Self { ids, index, offsets, targets, weights }
+    }
+}
+
+impl<N: GraphNode> From<&HealthGraph<N>> for CsrGraph<N::Id> {
+    /// Freezes a graph, its payloads stay in the HealthGraph.
+    /// Panics if the graph has more than u32::MAX nodes.
+    fn from(graph: &HealthGraph<N>) -> Self {
+        Self::from_adjacency(graph)
+    }
+}
+
+impl<Id: Copy + Eq + Hash + Ord + Debug> Adjacency for CsrGraph<Id> {
+    type Id = Id;
+    fn node_count(&self) -> usize {
+        self.ids.len()
+    }
+    fn edge_count(&self) -> usize {
+        self.targets.len() / 2 //every edge is stored at both of its ends
+    }
+    fn node_ids(&self) -> impl Iterator<Item = Id> + '_ {
+        self.ids.iter().copied()
+    }
+    fn degree(&self, id: Id) -> usize {
+        self.index_of(id).map_or(0, |index| self.neighbors_at(index).len())
+    }
+    fn weighted_neighbors(&self, id: Id) -> impl Iterator<Item = (Id, f64)> + '_ {
+        let index = self.index_of(id);
+        let neighbors = index.map_or(&[][..], |index| self.neighbors_at(index));
+        let weights = index.and_then(|index| self.weights_at(index));
+        neighbors.iter().enumerate().map(move |(i, &n)| (self.ids[n as usize], weights.map_or(1.0, |weights| weights[i])))
+    }
+}
@@ -0,0 +1,79 @@
+//This module computes the Gower distance between two full health records.
+//Gower's coefficient mixes every kind of column: nominal and binary answers count as the same or different,
+//ordinal and continuous answers count their distance over the range of the column in the dataset.
+//A column missing in either record is left out of the mean, so records with gaps can still be compared.
+
+use crate::codebook::CodeBook;
+use crate::schema::{Column, ColumnKind, HealthRecord, Response, COLUMNS};
+
+//a column of the model with the range of its valid values in the dataset
+#[derive(Debug, Clone, Copy)]
+struct Fitted {
+    column: &'static Column,
+    get: fn(&HealthRecord) -> Response<u32>, //reads the column from a record
+    kind: ColumnKind, //kind given by the code book, ordinal codes count their distance
+    range: f64, //largest minus smallest valid value, 0 if the column has at most one distinct value
+}
+
+///Gower distance over a set of columns, with the ranges of the ordinal and continuous columns
+///computed from the dataset it was fitted on
+#[derive(Debug, Clone)]
+pub struct GowerModel {
+    columns: Vec<Fitted>,
+}
+
+impl GowerModel {
+    /// Fits a model on every column of the schema except ADM_RNO1.
+    pub fn fit<'a>(records: impl IntoIterator<Item = &'a HealthRecord> + Clone, codebook: &CodeBook) -> Self {
+        Self::fit_columns(records, COLUMNS, codebook)
+    }
+    /// Fits a model on the given columns, computing the range of each one from the valid values of the records.
+    /// The code book says which categorical columns are ordinal, see CodeBook::kind. ADM_RNO1 is ignored.
+    pub fn fit_columns<'a>(
+        records: impl IntoIterator<Item = &'a HealthRecord> + Clone,
+        columns: impl IntoIterator<Item = &'static Column>,
+        codebook: &CodeBook,
+    ) -> Self {
+        let columns = columns.into_iter()
+            .filter_map(|column| Some((column, HealthRecord::accessor(column.name)?))) //skips ADM_RNO1
+            .map(|(column, get)| {
+                let values = records.clone().into_iter().filter_map(|record| get(record).value());
+                let (low, high) = values.fold((u32::MAX, u32::MIN), |(low, high), v| (low.min(v), high.max(v)));
+                let range = if low < high { (high - low) as f64 } else { 0.0 };
+                Fitted { column, get, kind: codebook.kind(column), range }
+            })
+            .collect();
+        Self { columns }
+    }
+    /// Returns the names of the columns of the model.
+    pub fn columns(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.columns.iter().map(|fitted| fitted.column.name)
+    }
+    /// Returns the range of a column in the fitted dataset, None if the column is not in the model.
+    pub fn range(&self, column: &str) -> Option<f64> {
+        self.columns.iter().find(|fitted| fitted.column.name == column).map(|fitted| fitted.range)
+    }
+    /// Computes the Gower distance of two records in [0, 1], the mean of the column distances
+    /// over the columns answered in both records. Returns None if no column was answered in both.
+    pub fn distance(&self, a: &HealthRecord, b: &HealthRecord) -> Option<f64> {
+        let mut total = 0.0;
+        let mut count = 0;
+        for fitted in &self.columns {
+            let (Some(x), Some(y)) = ((fitted.get)(a).value(), (fitted.get)(b).value()) else {
+                continue; //missing in one of the records
+            };
+            total += match fitted.kind {
+                ColumnKind::Nominal | ColumnKind::Binary | ColumnKind::Id => if x == y { 0.0 } else { 1.0 },
+                ColumnKind::Ordinal | ColumnKind::Continuous if fitted.range > 0.0 => (x.abs_diff(y) as f64 / fitted.range).min(1.0),
+                ColumnKind::Ordinal | ColumnKind::Continuous => 0.0, //the column is constant in the fitted data
+            };
+            count += 1;
+        }
+        (count > 0).then(|| total / count as f64)
+    }
+    /// Computes the Gower similarity of two records, 1 minus their distance, usable as an edge weight.
+    /// Returns 0.0 if no column was answered in both records.
+    pub fn similarity(&self, a: &HealthRecord, b: &HealthRecord) -> f64 {
+        self.distance(a, b).map_or(0.0, |distance| 1.0 - distance)
+    }
+}
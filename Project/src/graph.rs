@@ -1,36 +1,120 @@
 //This module defines the graph structure and its methods for managing the PersonNodes and edges(relationships).
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use crate::schema::{HealthRecord, Response};
 
 //represents levels of activity
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ActivityLevel {
     Low,
     Medium,
     High,
     Unknown,
 }
+
+///weekly minute cut points used to derive the ActivityLevel of a person
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActivityThresholds {
+    pub medium: u32, //minutes from which a person is Medium(150, the WHO minimum)
+    pub high: u32, //minutes from which a person is High(300)
+    pub count_vigorous_twice: bool, //add Physical_vigorous_act_time counted twice, as WHO moderate-equivalent minutes
+}
+
+impl Default for ActivityThresholds {
+    fn default() -> Self {
+        Self { medium: 150, high: 300, count_vigorous_twice: false }
+    }
+}
+
+impl ActivityLevel {
+    /// Derives the activity level from the weekly physical activity and vigorous activity minutes.
+    /// With count_vigorous_twice the minutes are moderate-equivalent(MVPA) minutes, total + 2 × vigorous:
+    /// the WHO guidelines ask for 150 moderate or 75 vigorous minutes a week, so a vigorous minute counts as two.
+    /// The data dictionary gives Physical_vigorous_act_time as its own question(0 to 4860 minutes)
+    /// apart from Total_physical_act_time(sports, fitness and recreation, 0 to 2100), so both are added.
+    /// A missing activity time(reserve code or invalid cell) gives Unknown, a missing vigorous time adds nothing.
+    pub fn from_minutes(total: Response<u32>, vigorous: Response<u32>, thresholds: &ActivityThresholds) -> Self {
+        let Some(mut minutes) = total.value() else {
+            return ActivityLevel::Unknown;
+        };
+        if thresholds.count_vigorous_twice {
+            //saturating, a custom code book without a range lets any value through
+            minutes = minutes.saturating_add(vigorous.value().unwrap_or(0).saturating_mul(2));
+        }
+        if minutes >= thresholds.high {
+            ActivityLevel::High
+        } else if minutes >= thresholds.medium {
+            ActivityLevel::Medium
+        } else {
+            ActivityLevel::Low
+        }
+    }
+}
 ///represents a person in the health graph and their health and lifestyle attributes
+///every answer can be missing, with the reason given by the survey's reserve codes
 #[derive(Debug, Clone)]
 pub struct PersonNode {
     pub id: usize,
-    pub weight_state: u8,
+    pub weight_state: Response<u8>,
     pub activity_level: ActivityLevel,
-    pub life_satisfaction: u8,
-    pub gen_health_state: u8,
-    pub total_income: u8,
-    pub food_security: u8,
-    pub high_bp: bool,
-    pub high_cholesterol: bool,
-    pub diabetic: bool,
+    pub life_satisfaction: Response<u8>,
+    pub gen_health_state: Response<u8>,
+    pub total_income: Response<u8>,
+    pub food_security: Response<u8>,
+    pub high_bp: Response<bool>,
+    pub high_cholesterol: Response<bool>,
+    pub diabetic: Response<bool>,
+    pub record: HealthRecord, //every column of the row this person was loaded from
 }
-//undirected graph representing the similarities between people
-pub struct HealthGraph {
-    pub nodes: HashMap<usize, PersonNode>, // maps node id to PersonNode
-    pub edges: HashMap<usize, HashSet<usize>>, //adjacency list
+///a payload stored in a HealthGraph, identified by a small copyable id
+///people, households or health regions can all be nodes of a graph
+pub trait GraphNode {
+    type Id: Copy + Eq + Hash + Ord + Debug;
+    /// Returns the id of the node, unique within a graph.
+    fn id(&self) -> Self::Id;
+}
+
+impl GraphNode for PersonNode {
+    type Id = usize;
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+///read access to the nodes and weighted edges of an undirected graph, used by the analysis functions
+///so they run on the mutable HealthGraph and on the compact CsrGraph alike
+pub trait Adjacency {
+    type Id: Copy + Eq + Hash + Ord + Debug;
+    /// Returns the number of nodes.
+    fn node_count(&self) -> usize;
+    /// Returns the number of edges.
+    fn edge_count(&self) -> usize;
+    /// Returns the ids of every node.
+    fn node_ids(&self) -> impl Iterator<Item = Self::Id> + '_;
+    /// Returns the number of neighbors of a node, 0 for an unknown node.
+    fn degree(&self, id: Self::Id) -> usize;
+    /// Returns the neighbors of a node with the weights of the edges to them, nothing for an unknown node.
+    fn weighted_neighbors(&self, id: Self::Id) -> impl Iterator<Item = (Self::Id, f64)> + '_;
+    /// Returns the neighbors of a node, nothing for an unknown node.
+    fn neighbor_ids(&self, id: Self::Id) -> impl Iterator<Item = Self::Id> + '_ {
+        self.weighted_neighbors(id).map(|(neighbor, _)| neighbor)
+    }
+    /// Returns the sum of the edge weights of a node.
+    fn strength(&self, id: Self::Id) -> f64 {
+        self.weighted_neighbors(id).fold(0.0, |total, (_, weight)| total + weight)
+    }
+}
+
+//undirected graph representing the similarities between nodes, people by default
+//every edge has a positive weight, the similarity of its two nodes(1.0 for an unweighted edge)
+pub struct HealthGraph<N: GraphNode = PersonNode> {
+    pub nodes: HashMap<N::Id, N>, // maps node id to its payload
+    pub edges: HashMap<N::Id, HashMap<N::Id, f64>>, //adjacency list, each neighbor with the weight of the edge to it
 }
 
-impl HealthGraph {
+impl<N: GraphNode> HealthGraph<N> {
     /// Creates a new HealthGraph instance.
     pub fn new() -> Self {
         Self {
@@ -38,31 +122,85 @@ impl HealthGraph {
             edges: HashMap::new(),
         }
     }
-    //adds a new person node to the graph
-    pub fn add_node(&mut self, person: PersonNode) {
-        let id = person.id;
-        self.nodes.insert(id, person); //insert the person into the graph
-        self.edges.insert(id, HashSet::new()); //initialize the adjacency list for the new node
+    //adds a new node to the graph
+    pub fn add_node(&mut self, node: N) {
+        let id = node.id();
+        self.nodes.insert(id, node); //insert the node into the graph
+        self.edges.insert(id, HashMap::new()); //initialize the adjacency list for the new node
     }
-    //adds an edge between two nodes in the graph
-    pub fn add_edge(&mut self, from: usize, to: usize) {
+    //adds an edge of weight 1.0 between two nodes in the graph
+    pub fn add_edge(&mut self, from: N::Id, to: N::Id) {
+        self.add_weighted_edge(from, to, 1.0);
+    }
+    //adds an edge with the given weight between two nodes in the graph, or updates its weight
+    //weights must be in (0, ∞): the weighted analyses use 1/weight as the length of an edge
+    //nothing is added if one of the nodes is not in the graph, for a self-loop(from == to) or if the weight is zero, negative, infinite or NaN
+    pub fn add_weighted_edge(&mut self, from: N::Id, to: N::Id, weight: f64) {
+        if !self.nodes.contains_key(&from) || !self.nodes.contains_key(&to) {
+            return;
+        }
+        if from == to { //a loop would be counted once by total_edges and skew the strength and modularity
+            return;
+        }
+        if !(weight.is_finite() && weight > 0.0) {
+            return;
+        }
         if let Some(neighbors1) = self.edges.get_mut(&from) { //get the neighbors of the first node
-            neighbors1.insert(to); //insert the second node into the adjacency list of the first node
+            neighbors1.insert(to, weight); //insert the second node into the adjacency list of the first node
         }
         if let Some(neighbors2) = self.edges.get_mut(&to) { //get the neighbors of the second node
-            neighbors2.insert(from); //insert the first node into the adjacency list of the second node
+            neighbors2.insert(from, weight); //insert the first node into the adjacency list of the second node
         }
     }
-    //gets the neighbors of a node and returns them
-    pub fn neighbors(&self, id: usize) -> Option<&HashSet<usize>> {
+    //gets the weight of the edge between two nodes, None if they are not connected
+    pub fn weight(&self, a: N::Id, b: N::Id) -> Option<f64> {
+        self.edges.get(&a)?.get(&b).copied()
+    }
+    //gets the neighbors of a node with the weights of the edges to them and returns them
+    pub fn neighbors(&self, id: N::Id) -> Option<&HashMap<N::Id, f64>> {
         self.edges.get(&id)
     }
     //computes the degree/# of neighbors of a node and returns it
-    pub fn degree(&self, id: usize) -> usize {
+    pub fn degree(&self, id: N::Id) -> usize {
         self.edges.get(&id).map_or(0, |neighbors| neighbors.len()) //returns the number of neighbors of the node
     }
+    //computes the strength/sum of the edge weights of a node and returns it, the degree for an unweighted graph
+    pub fn strength(&self, id: N::Id) -> f64 {
+        self.neighbors(id).map_or(0.0, |neighbors| neighbors.values().fold(0.0, |total, w| total + w))
+    }
     //computes the total number of edges in the graph and returns it
     pub fn total_edges(&self) -> usize {
         self.edges.values().map(|s| s.len()).sum::<usize>() / 2 //divided by 2 because the graph is undirected
     }
-}
\ No newline at end of file
+}
+
+impl<N: GraphNode> Default for HealthGraph<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: GraphNode> Adjacency for HealthGraph<N> {
+    type Id = N::Id;
+    fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+    fn edge_count(&self) -> usize {
+        self.total_edges()
+    }
+    fn node_ids(&self) -> impl Iterator<Item = N::Id> + '_ {
+        self.nodes.keys().copied()
+    }
+    fn degree(&self, id: N::Id) -> usize {
+        HealthGraph::degree(self, id)
+    }
+    fn weighted_neighbors(&self, id: N::Id) -> impl Iterator<Item = (N::Id, f64)> + '_ {
+        self.neighbors(id).into_iter().flatten().map(|(&neighbor, &weight)| (neighbor, weight))
+    }
+    fn neighbor_ids(&self, id: N::Id) -> impl Iterator<Item = N::Id> + '_ {
+        self.neighbors(id).into_iter().flatten().map(|(&neighbor, _)| neighbor)
+    }
+    fn strength(&self, id: N::Id) -> f64 {
+        HealthGraph::strength(self, id)
+    }
+}
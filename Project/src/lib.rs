@@ -0,0 +1,44 @@
+//! Builds similarity graphs of CCHS respondents and analyzes them.
+//!
+//! The usual pipeline loads the dataset, draws a sample, builds the graph and analyzes it:
+//!
+//! ```no_run
+//! use health_graph::{analyze, build_graph, draw, load_people_with, LoadOptions, SampleDesign};
+//! use rand::SeedableRng;
+//!
+//! let (people, report) = load_people_with("health_dataset.csv", &LoadOptions::default())?;
+//! let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(42);
+//! let sample = draw(people, 10000, &SampleDesign::default(), &mut rng)?;
+//! let graph = build_graph(&sample);
+//! println!("{} rows kept, {:?}", report.rows_kept, analyze(&graph));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! The modules stay public for finer control: the schema and code book of the dataset,
+//! the similarity rules and every analysis function.
+
+pub mod schema;
+pub mod codebook;
+pub mod parser;
+pub mod graph;
+pub mod similarity;
+pub mod analysis;
+pub mod centrality;
+pub mod community;
+pub mod sampling;
+pub mod gower;
+pub mod builder;
+pub mod csr;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+#[cfg(test)]
+mod tests;
+
+pub use builder::{build, build_graph, build_graph_blocked_with, build_graph_with, build_scored_graph, build_weighted_graph_with, GraphOptions};
+pub use analysis::{graph_stats as analyze, health_by_income_and_food_security, GraphStats, HealthGroup};
+pub use codebook::CodeBook;
+pub use csr::CsrGraph;
+pub use graph::{Adjacency, GraphNode, HealthGraph, PersonNode};
+pub use parser::{load_people, load_people_with, LoadOptions, ParsePolicy, ValidationReport};
+pub use sampling::{draw, SampleDesign};
+pub use similarity::{is_similar, similarity_score};
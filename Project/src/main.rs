@@ -1,60 +1,405 @@
 // This is the entry point of the program
 //It loads the dataset and parses it into Person records
 //It creates a graph of the people and their connections based on similarity and performs analysis
+//The command line arguments choose which analysis is run(see cli.rs), the work itself is done by the health_graph library(see lib.rs)
 
-mod parser;
-mod graph;
-mod similarity;
-mod analysis;
+mod cli;
 #[cfg(test)]
-mod tests;
+mod cli_tests;
 
-use parser::load_people;
-use graph::HealthGraph;
-use similarity::is_similar;
-use analysis::{average_degree, node_w_highest_degree, average_shortest_path_length};
-use rand::seq::SliceRandom;
-use rand::thread_rng;
-use analysis::analyze_health_by_income_and_food_security;
+use std::error::Error;
+use std::fs::File;
+use std::io;
+use clap::Parser;
+use serde::Serialize;
+use serde::ser::{SerializeStruct, Serializer};
+use health_graph::{build, load_people_with, sampling, CodeBook, CsrGraph, GraphOptions, HealthGraph, LoadOptions, PersonNode, ValidationReport};
+#[cfg(not(feature = "parallel"))]
+use health_graph::analysis::{estimated_graph_stats, graph_stats};
+#[cfg(feature = "parallel")] //same results, on every core
+use health_graph::parallel::{estimated_graph_stats, graph_stats};
+use health_graph::gower::GowerModel;
+use health_graph::similarity::SimilarityConfig;
+use health_graph::analysis::{analyze_health_by_income_and_food_security, connected_components, health_by_income_and_food_security, GraphStats, HealthGroup, HealthRates};
+use health_graph::centrality::{self, top_k, DEFAULT_DAMPING};
+use health_graph::community::{health_by_community, label_propagation, louvain, CommunityHealth};
+use cli::{CentralityArgs, Cli, Command, CommunityArgs, ExportArgs, ExportKind, Format, GraphArgs, InputArgs, Measure, Method, write_rows};
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+//a node of the exported graph
+#[derive(Serialize)]
+struct NodeRow<'a> {
+    seed: u64, //seed of the sample, on every row so the file alone reproduces the graph
+    id: usize,
+    adm_rno1: &'a str,
+    degree: usize,
+    strength: f64,
+    component: usize, //connected component, 0 is the largest
+}
+
+//size of a built graph and the seed of its sample
+#[derive(Serialize)]
+struct GraphSize {
+    seed: u64,
+    nodes: usize,
+    edges: usize,
+}
+
+//statistics of a built graph and the seed of its sample
+#[derive(Serialize)]
+struct StatsRow {
+    seed: u64,
+    nodes: usize,
+    edges: usize,
+    average_degree: f64,
+    average_strength: f64,
+    highest_degree_node: Option<usize>,
+    highest_degree: usize,
+    average_shortest_path_length: f64,
+    path_length_margin: Option<f64>,
+    path_sources: Option<usize>,
+    components: usize,
+    giant_component_fraction: f64,
+    unreachable_pairs: usize,
+}
+
+impl StatsRow {
+    fn new(seed: u64, stats: &GraphStats) -> Self {
+        Self {
+            seed,
+            nodes: stats.nodes,
+            edges: stats.edges,
+            average_degree: stats.average_degree,
+            average_strength: stats.average_strength,
+            highest_degree_node: stats.highest_degree_node,
+            highest_degree: stats.highest_degree,
+            average_shortest_path_length: stats.average_shortest_path_length,
+            path_length_margin: stats.path_length_margin,
+            path_sources: stats.path_sources,
+            components: stats.components,
+            giant_component_fraction: stats.giant_component_fraction,
+            unreachable_pairs: stats.unreachable_pairs,
+        }
+    }
+}
+
+//results of the run without a subcommand, written as JSON
+#[derive(Serialize)]
+struct AllRow<'a> {
+    stats: StatsRow,
+    health_groups: &'a [HealthGroup],
+}
+
+//a person ranked by a centrality measure
+#[derive(Serialize)]
+struct CentralityRow<'a> {
+    seed: u64, //seed of the sample, like the exported rows
+    rank: usize,
+    id: usize,
+    adm_rno1: &'a str,
+    score: f64,
+}
+
+//health condition rates of a community and the seed of the sample
+struct CommunityRow<'a> {
+    seed: u64,
+    health: &'a CommunityHealth,
+}
+
+impl Serialize for CommunityRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut row = serializer.serialize_struct("CommunityRow", 2 + HealthRates::FIELDS)?;
+        row.serialize_field("seed", &self.seed)?;
+        row.serialize_field("community", &self.health.community)?;
+        self.health.rates.serialize_fields(&mut row)?;
+        row.end()
+    }
+}
+
+//an edge of the exported graph
+#[derive(Serialize)]
+struct EdgeRow {
+    seed: u64, //seed of the sample, on every row so the file alone reproduces the graph
+    source: usize,
+    target: usize,
+    weight: f64,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(&cli) { //print the error message, not its Debug form
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+//runs the command chosen on the command line
+fn run(cli: &Cli) -> Result<(), Box<dyn Error>> {
+    match &cli.command {
+        None => run_all(&cli.graph, cli.format),
+        Some(Command::Validate(args)) => run_validate(args, cli.format),
+        Some(Command::BuildGraph(args)) => run_build_graph(args, cli.format),
+        Some(Command::Stats(args)) => run_stats(args, cli.format),
+        Some(Command::Crosstab(args)) => run_crosstab(args, cli.format),
+        Some(Command::Export(args)) => run_export(args, cli.format),
+        Some(Command::Centrality(args)) => run_centrality(args, cli.format),
+        Some(Command::Communities(args)) => run_communities(args, cli.format),
+    }
+}
+
+//loads the dataset with the options given on the command line, reading the survey weights from the given column
+fn load(args: &InputArgs, weight_column: Option<&str>) -> Result<(Vec<PersonNode>, ValidationReport, LoadOptions), Box<dyn Error>> {
+    let mut options = LoadOptions::with_policy(args.policy.into());
+    options.weight_column = weight_column.map(|name| name.to_string());
+    if let Some(path) = &args.codebook {
+        options.codebook = CodeBook::from_file(path)?; //use the given code book instead of the bundled one
+    }
+    let (people, report) = load_people_with(&args.input, &options)?;
+    Ok((people, report, options))
+}
+
+//returns the seed given on the command line, or draws a new one so the run can still be reproduced
+fn sample_seed(args: &GraphArgs) -> u64 {
+    args.seed.unwrap_or_else(|| thread_rng().gen())
+}
+
+//draws the sample chosen on the command line with a seeded RNG
+//the same seed, options and dataset always give the same sample
+fn sample(people: Vec<PersonNode>, args: &GraphArgs, seed: u64) -> Result<Vec<PersonNode>, Box<dyn Error>> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    Ok(sampling::draw(people, args.sample_size, &args.design(), &mut rng)?)
+}
+
+//returns the graph options chosen on the command line, loading the profile and fitting the Gower model if asked
+//the Gower model is fitted on the whole dataset, not on the sample
+fn graph_options(people: &[PersonNode], args: &GraphArgs, codebook: &CodeBook) -> Result<GraphOptions, Box<dyn Error>> {
+    Ok(GraphOptions {
+        threshold: args.threshold,
+        profile: args.profile.as_deref().map(SimilarityConfig::from_file).transpose()?,
+        gower: args.gower.then(|| GowerModel::fit(people.iter().map(|person| &person.record), codebook)),
+    })
+}
+
+//loads the dataset, takes the sample and builds its graph, printing progress in text mode
+//returns the graph and the seed of the sample
+fn load_graph(args: &GraphArgs, format: Format) -> Result<(HealthGraph, u64), Box<dyn Error>> {
+    let (people, report, options) = load(&args.input, args.weights.as_deref())?;
+    let graph_options = graph_options(&people, args, &options.codebook)?;
+    let seed = sample_seed(args);
+    let people = sample(people, args, seed)?;
+    let graph = build(&people, &graph_options)?;
+    if format == Format::Text {
+        println!("{} people were loaded, {} were selected for the graph with seed {}.", report.rows_kept, people.len(), seed);
+    }
+    Ok((graph, seed))
+}
+
+//runs every analysis and prints it as text, or writes the statistics and the health groups as CSV or JSON
+fn run_all(args: &GraphArgs, format: Format) -> Result<(), Box<dyn Error>> {
     //load the dataset from CSV file
-    let mut people = load_people("health_dataset.csv")?;
-    println!("{} people were loaded.", people.len());
-    //shuffle the dataset and take a sample of 10000 people
-    let mut rng = thread_rng();
-    people.shuffle(&mut rng); //shuffle the dataset
-    let people = people.into_iter().take(10000).collect::<Vec<_>>();
-    println!("{} people were selected for the graph.", people.len());
-    //create a graph of the people and their connections based on similarity
-    let mut graph = HealthGraph::new();
-    for person in &people {
-        graph.add_node(person.clone());
-    }
-    println!("{} nodes were added to the graph.", graph.nodes.len());
-    //add edges to the graph based on similarity
-    let ids: Vec<_> = graph.nodes.keys().copied().collect(); //collect the ids of all nodes
-    for i in 0..ids.len() { //iterate over all nodes
-        for j in (i+1)..ids.len() { //compare each node with all other nodes
-            if is_similar(&graph.nodes[&ids[i]], &graph.nodes[&ids[j]]) { //check if the two nodes are similar
-                graph.add_edge(ids[i], ids[j]); //add an edge between the two nodes
-            }
+    let (people, report, options) = load(&args.input, args.weights.as_deref())?;
+    let text = format == Format::Text; //progress lines would mix with the rows
+    if text {
+        println!("{} people were loaded.", people.len());
+        if !report.is_clean() { //invalid cells were treated as missing values
+            println!("{} invalid cells were treated as missing, {} rows were skipped.", report.issues.len(), report.rows_skipped());
         }
     }
-    println!("{} edges and {} nodes were added to the graph.", graph.total_edges(), graph.nodes.len());
-    //analyze the graph
+    let graph_options = graph_options(&people, args, &options.codebook)?;
+    //shuffle the dataset and take a sample of people
+    let seed = sample_seed(args);
+    let people = sample(people, args, seed)?;
+    let graph = build(&people, &graph_options)?;
+    //analyze the frozen graph, smaller and faster to walk
+    let stats = stats_of(&CsrGraph::from(&graph), args, seed);
+    match format {
+        Format::Text => {
+            println!("{} people were selected for the graph with seed {}.", people.len(), seed);
+            println!("{} edges and {} nodes were added to the graph.", graph.total_edges(), graph.nodes.len());
+            print_stats(&stats);
+            //analyze the health conditions of people based on their income and food security
+            analyze_health_by_income_and_food_security(&people, &options.codebook);
+        }
+        Format::Csv => { //two tables separated by an empty line
+            write_rows(&[StatsRow::new(seed, &stats)], format, io::stdout())?;
+            println!();
+            write_rows(&health_by_income_and_food_security(&people, &options.codebook), format, io::stdout())?;
+        }
+        Format::Json => {
+            let health_groups = health_by_income_and_food_security(&people, &options.codebook);
+            write_rows(&[AllRow { stats: StatsRow::new(seed, &stats), health_groups: &health_groups }], format, io::stdout())?;
+        }
+    }
+    Ok(())
+}
+
+//computes the graph statistics, estimating the path length from sampled sources if asked
+//the sources are drawn from a second stream of the sample seed, so the seed reproduces the whole run
+fn stats_of(graph: &CsrGraph, args: &GraphArgs, seed: u64) -> GraphStats {
+    match args.path_sources {
+        Some(sources) => {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream(1);
+            estimated_graph_stats(graph, sources, &mut rng)
+        }
+        None => graph_stats(graph),
+    }
+}
+
+//prints the graph analysis as text
+fn print_stats(stats: &GraphStats) {
     println!("\n--- Graph Analysis ---");
-    let avg_deg = average_degree(&graph);
-    println!("Average connections per person: {:.2}", avg_deg);
-    if let Some((id, deg)) = node_w_highest_degree(&graph) { //find the node with the highest degree
-        println!("Person {} has the highest degree: {}", id, deg);
-    }
-    else {
-        println!("No nodes in the graph or error finding a node with the highest degree.");
-    }
-    let avg_shortest_path = average_shortest_path_length(&graph);
-    println!("Average shortest path length: {:.2}", avg_shortest_path);
-    //analyze the health conditions of people based on their income and food security
-    analyze_health_by_income_and_food_security(&people);
+    println!("Average connections per person: {:.2}", stats.average_degree);
+    println!("Average connection strength per person: {:.2}", stats.average_strength);
+    match stats.highest_degree_node { //the node with the highest degree
+        Some(id) => println!("Person {} has the highest degree: {}", id, stats.highest_degree),
+        None => println!("No nodes in the graph or error finding a node with the highest degree."),
+    }
+    println!("Connected components: {}, the largest holds {:.1}% of the people", stats.components, stats.giant_component_fraction * 100.0);
+    if stats.unreachable_pairs > 0 { //the path length only covers the connected pairs
+        println!("{} pairs of people are not connected and are left out of the path length.", stats.unreachable_pairs);
+    }
+    match stats.path_length_margin {
+        Some(margin) => println!("Average shortest path length: {:.2} ± {:.2} (95% confidence, estimated from {} sources)",
+            stats.average_shortest_path_length, margin, stats.path_sources.unwrap_or(0)),
+        None => println!("Average shortest path length: {:.2}", stats.average_shortest_path_length),
+    }
+}
+
+//loads and validates the dataset and prints the rejected cells
+fn run_validate(args: &InputArgs, format: Format) -> Result<(), Box<dyn Error>> {
+    let (_, report, _) = load(args, None)?;
+    match format {
+        Format::Text => {
+            for issue in &report.issues {
+                println!("{}", issue);
+            }
+            println!("{} rows read, {} kept, {} skipped, {} invalid cells.", report.rows_read, report.rows_kept, report.rows_skipped(), report.issues.len());
+        }
+        _ => write_rows(&report.issues, format, io::stdout())?,
+    }
+    Ok(())
+}
+
+//builds the graph and prints its size
+fn run_build_graph(args: &GraphArgs, format: Format) -> Result<(), Box<dyn Error>> {
+    let (graph, seed) = load_graph(args, format)?;
+    match format {
+        Format::Text => println!("{} edges and {} nodes were added to the graph.", graph.total_edges(), graph.nodes.len()),
+        _ => write_rows(&[GraphSize { seed, nodes: graph.nodes.len(), edges: graph.total_edges() }], format, io::stdout())?,
+    }
+    Ok(())
+}
+
+//builds the graph and prints its statistics
+fn run_stats(args: &GraphArgs, format: Format) -> Result<(), Box<dyn Error>> {
+    let (graph, seed) = load_graph(args, format)?;
+    let stats = stats_of(&CsrGraph::from(&graph), args, seed);
+    match format {
+        Format::Text => print_stats(&stats),
+        _ => write_rows(&[StatsRow::new(seed, &stats)], format, io::stdout())?,
+    }
+    Ok(())
+}
+
+//prints the health conditions by income and food security
+fn run_crosstab(args: &InputArgs, format: Format) -> Result<(), Box<dyn Error>> {
+    let (people, _, options) = load(args, None)?;
+    match format {
+        Format::Text => analyze_health_by_income_and_food_security(&people, &options.codebook),
+        _ => write_rows(&health_by_income_and_food_security(&people, &options.codebook), format, io::stdout())?,
+    }
+    Ok(())
+}
+
+//builds the graph and writes its nodes or edges as CSV(text and csv formats) or JSON
+fn run_export(args: &ExportArgs, format: Format) -> Result<(), Box<dyn Error>> {
+    let (graph, seed) = load_graph(&args.graph, Format::Csv)?; //no progress lines, they would mix with the output
+    eprintln!("Exporting the graph of the sample with seed {}.", seed); //progress goes to stderr, the rows carry the seed
+    let writer: Box<dyn io::Write> = match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+    let mut ids: Vec<usize> = graph.nodes.keys().copied().collect();
+    ids.sort();
+    match args.what {
+        ExportKind::Nodes => {
+            let components = connected_components(&graph);
+            let rows: Vec<NodeRow> = ids.iter()
+                .map(|&id| NodeRow {
+                    seed,
+                    id,
+                    adm_rno1: &graph.nodes[&id].record.adm_rno1,
+                    degree: graph.degree(id),
+                    strength: graph.strength(id),
+                    component: components.component_of[&id],
+                })
+                .collect();
+            write_rows(&rows, format, writer)
+        }
+        ExportKind::Edges => {
+            let mut rows = Vec::new();
+            for &source in &ids {
+                let mut targets: Vec<(usize, f64)> = graph.neighbors(source).into_iter().flatten().map(|(&t, &w)| (t, w)).filter(|&(t, _)| t > source).collect();
+                targets.sort_by_key(|&(t, _)| t);
+                rows.extend(targets.into_iter().map(|(target, weight)| EdgeRow { seed, source, target, weight })); //each edge once
+            }
+            write_rows(&rows, format, writer)
+        }
+    }
+}
+
+//builds the graph and prints the people with the highest centrality
+fn run_centrality(args: &CentralityArgs, format: Format) -> Result<(), Box<dyn Error>> {
+    let (graph, seed) = load_graph(&args.graph, format)?;
+    let scores = match args.measure { //every measure freezes the graph into a CsrGraph
+        Measure::Betweenness => centrality::betweenness(&graph),
+        Measure::Closeness => centrality::closeness(&graph),
+        Measure::Harmonic => centrality::harmonic(&graph),
+        Measure::Eigenvector => centrality::eigenvector(&graph),
+        Measure::Pagerank => centrality::pagerank(&graph, DEFAULT_DAMPING),
+    };
+    let rows: Vec<CentralityRow> = top_k(&scores, args.top).into_iter().enumerate()
+        .map(|(i, (id, score))| CentralityRow { seed, rank: i + 1, id, adm_rno1: &graph.nodes[&id].record.adm_rno1, score })
+        .collect();
+    match format {
+        Format::Text => {
+            println!("\n--- {:?} centrality ---", args.measure);
+            for row in &rows {
+                println!("{}. Person {} (ADM_RNO1 {}): {:.4}", row.rank, row.id, row.adm_rno1, row.score);
+            }
+        }
+        _ => write_rows(&rows, format, io::stdout())?,
+    }
+    Ok(())
+}
+
+//builds the graph, finds its communities and prints the health conditions of the largest ones
+fn run_communities(args: &CommunityArgs, format: Format) -> Result<(), Box<dyn Error>> {
+    let (graph, seed) = load_graph(&args.graph, format)?;
+    let communities = match args.method {
+        Method::Louvain => louvain(&CsrGraph::from(&graph)),
+        Method::LabelPropagation => {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream(2); //apart from the sample and the path sources
+            label_propagation(&CsrGraph::from(&graph), &mut rng)
+        }
+    };
+    let mut groups = health_by_community(&graph, &communities);
+    groups.truncate(args.top);
+    match format {
+        Format::Text => {
+            println!("\n--- Communities ---");
+            println!("{} communities were found, modularity {:.3}.", communities.count(), communities.modularity);
+            for group in &groups {
+                println!("Community {} | {}", group.community, group.rates);
+            }
+        }
+        _ => {
+            let rows: Vec<CommunityRow> = groups.iter().map(|health| CommunityRow { seed, health }).collect();
+            write_rows(&rows, format, io::stdout())?
+        }
+    }
     Ok(())
 }
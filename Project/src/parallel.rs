@@ -0,0 +1,123 @@
+//This module has parallel versions of the graph builders and of the all-pairs path statistics, using rayon.
+//It is only compiled with the parallel feature, the results are the same as the serial versions'.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use rayon::prelude::*;
+use rand::Rng;
+use crate::analysis::{distance_total, graph_stats_with_path_length, path_estimate, sample_sources, GraphStats, PathEstimate};
+use crate::builder::blocking_key;
+use crate::graph::{Adjacency, GraphNode, HealthGraph, PersonNode};
+use crate::similarity::{is_similar, similarity_score};
+
+//compares every id with the ids after it in parallel and returns the pairs the rule gives a value for
+fn pairs<Id: Copy + Send + Sync, T: Send>(ids: &[Id], compare: impl Fn(Id, Id) -> Option<T> + Sync) -> Vec<(Id, Id, T)> {
+    (0..ids.len()).into_par_iter()
+        .flat_map_iter(|i| {
+            let compare = &compare;
+            ids[i + 1..].iter().filter_map(move |&j| compare(ids[i], j).map(|value| (ids[i], j, value)))
+        })
+        .collect()
+}
+
+//creates a graph holding the nodes, without edges
+fn graph_of<N: GraphNode + Clone>(nodes: &[N]) -> HealthGraph<N> {
+    let mut graph = HealthGraph::new();
+    for node in nodes {
+        graph.add_node(node.clone());
+    }
+    graph
+}
+
+/// Parallel version of builder::build_graph.
+pub fn build_graph(people: &[PersonNode]) -> HealthGraph {
+    build_graph_blocked_with(people, blocking_key, is_similar)
+}
+
+/// Parallel version of builder::build_scored_graph.
+pub fn build_scored_graph(people: &[PersonNode], threshold: f64) -> HealthGraph {
+    build_weighted_graph_with(people, similarity_score, threshold)
+}
+
+/// Parallel version of builder::build_graph_with.
+pub fn build_graph_with<N>(nodes: &[N], similar: impl Fn(&N, &N) -> bool + Sync) -> HealthGraph<N>
+where N: GraphNode + Clone + Sync, N::Id: Send + Sync {
+    let mut graph = graph_of(nodes);
+    let ids: Vec<_> = graph.nodes.keys().copied().collect();
+    let edges = pairs(&ids, |a, b| similar(&graph.nodes[&a], &graph.nodes[&b]).then_some(()));
+    for (a, b, ()) in edges {
+        graph.add_edge(a, b);
+    }
+    graph
+}
+
+/// Parallel version of builder::build_weighted_graph_with.
+pub fn build_weighted_graph_with<N>(nodes: &[N], score: impl Fn(&N, &N) -> f64 + Sync, threshold: f64) -> HealthGraph<N>
+where N: GraphNode + Clone + Sync, N::Id: Send + Sync {
+    let mut graph = graph_of(nodes);
+    let ids: Vec<_> = graph.nodes.keys().copied().collect();
+    let edges = pairs(&ids, |a, b| Some(score(&graph.nodes[&a], &graph.nodes[&b])).filter(|&weight| weight >= threshold && weight > 0.0));
+    for (a, b, weight) in edges {
+        graph.add_weighted_edge(a, b, weight);
+    }
+    graph
+}
+
+/// Parallel version of builder::build_graph_blocked_with, the blocks and the nodes of each block are compared in parallel.
+pub fn build_graph_blocked_with<N, K: Hash + Eq>(nodes: &[N], key: impl Fn(&N) -> Option<K>, similar: impl Fn(&N, &N) -> bool + Sync) -> HealthGraph<N>
+where N: GraphNode + Clone + Sync, N::Id: Send + Sync {
+    let mut graph = graph_of(nodes);
+    let mut blocks: HashMap<K, Vec<N::Id>> = HashMap::new(); //ids of the nodes sharing a key
+    for node in nodes {
+        if let Some(key) = key(node) {
+            blocks.entry(key).or_default().push(node.id());
+        }
+    }
+    let blocks: Vec<Vec<N::Id>> = blocks.into_values().collect();
+    let edges: Vec<_> = blocks.par_iter()
+        .flat_map_iter(|ids| pairs(ids, |a, b| similar(&graph.nodes[&a], &graph.nodes[&b]).then_some(())))
+        .collect();
+    for (a, b, ()) in edges {
+        graph.add_edge(a, b);
+    }
+    graph
+}
+
+/// Parallel version of analysis::average_shortest_path_length, with one BFS per node spread over the cores.
+pub fn average_shortest_path_length<G>(graph: &G) -> f64
+where G: Adjacency + Sync, G::Id: Send + Sync {
+    let ids: Vec<G::Id> = graph.node_ids().collect();
+    let (total_distance, count) = ids.par_iter()
+        .map(|&start_id| distance_total(graph, start_id))
+        .reduce(|| (0, 0), |a, b| (a.0 + b.0, a.1 + b.1)); //integer sums, so the order does not change the result
+    if count == 0 {
+        0.0
+    } else {
+        total_distance as f64 / count as f64
+    }
+}
+
+/// Parallel version of analysis::graph_stats.
+pub fn graph_stats<G>(graph: &G) -> GraphStats<G::Id>
+where G: Adjacency + Sync, G::Id: Send + Sync {
+    graph_stats_with_path_length(graph, average_shortest_path_length(graph))
+}
+
+/// Parallel version of analysis::estimate_average_shortest_path_length, the sources are drawn like the serial version's.
+pub fn estimate_average_shortest_path_length<G>(graph: &G, sources: usize, rng: &mut impl Rng) -> PathEstimate
+where G: Adjacency + Sync, G::Id: Send + Sync {
+    let starts = sample_sources(graph, sources, rng);
+    let totals: Vec<(usize, usize)> = starts.par_iter().map(|&start_id| distance_total(graph, start_id)).collect(); //keeps the order of the sources
+    path_estimate(graph.node_count(), &totals)
+}
+
+/// Parallel version of analysis::estimated_graph_stats.
+pub fn estimated_graph_stats<G>(graph: &G, sources: usize, rng: &mut impl Rng) -> GraphStats<G::Id>
+where G: Adjacency + Sync, G::Id: Send + Sync {
+    let estimate = estimate_average_shortest_path_length(graph, sources, rng);
+    GraphStats {
+        path_length_margin: Some(estimate.margin),
+        path_sources: Some(estimate.sources),
+        ..graph_stats_with_path_length(graph, estimate.mean)
+    }
+}
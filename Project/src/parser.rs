@@ -1,63 +1,340 @@
 //this module defines the parser for the CSV file containing health data
 
+use std::collections::HashMap;
 use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::io::BufReader;
-use csv::ReaderBuilder;
-use crate::graph::{PersonNode, ActivityLevel};
+use csv::{ReaderBuilder, StringRecord};
+use serde::Serialize;
+use crate::graph::{PersonNode, ActivityLevel, ActivityThresholds};
+use crate::schema::{Column, ColumnKind, HealthRecord, Missing, Response, COLUMNS};
+use crate::codebook::CodeBook;
 
-/// Inputs a CSV file and parses it into a vector of PersonNode.
-/// Returns a vector of PersonNode or an error if the file cannot be read.
-///Uses the csv crate to read the CSV file.
+//what the loader should do with a cell that fails validation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParsePolicy {
+    FailFast,        //stop at the first invalid cell and return it as the error
+    SkipRow,         //drop every row that has an invalid cell
+    CoerceToMissing, //keep the row and treat the invalid cell as missing
+}
 
-pub fn load_people(path: &str) -> Result<Vec<PersonNode>, Box<dyn Error>> {
+//why a cell was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueReason {
+    Empty,      //the cell is blank
+    NotANumber, //the cell could not be parsed as a number
+    OutOfRange, //the number is neither a valid answer nor a reserve code in the code book
+}
+
+///a rejected cell: the data row(0-based, header excluded), its column, the raw text and why it was rejected
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParseIssue {
+    pub row: usize,
+    pub column: &'static str,
+    pub raw: String,
+    pub reason: IssueReason,
+}
+
+impl fmt::Display for ParseIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self.reason {
+            IssueReason::Empty => "is empty",
+            IssueReason::NotANumber => "is not a number",
+            IssueReason::OutOfRange => "is out of range",
+        };
+        write!(f, "row {}, column {}: {:?} {}", self.row, self.column, self.raw, reason)
+    }
+}
+
+impl Error for ParseIssue {}
+
+///summary of a validating load: how many rows were read and kept and every rejected cell
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub rows_read: usize,
+    pub rows_kept: usize,
+    pub issues: Vec<ParseIssue>,
+}
+
+impl ValidationReport {
+    /// Returns the number of rows dropped by the SkipRow policy.
+    pub fn rows_skipped(&self) -> usize {
+        self.rows_read - self.rows_kept
+    }
+    /// Returns true if no cell was rejected.
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+//checks a raw cell against the code book and returns the reason it is rejected, if any
+fn check_cell(column: &Column, raw: &str, codebook: &CodeBook) -> Option<IssueReason> {
+    if column.kind == ColumnKind::Id {
+        return None; //record numbers are kept as text
+    }
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Some(IssueReason::Empty);
+    }
+    match raw.parse::<u32>() {
+        Ok(value) if codebook.accepts(column.name, value) => None,
+        Ok(_) => Some(IssueReason::OutOfRange),
+        Err(_) => Some(IssueReason::NotANumber),
+    }
+}
+
+//columns every PersonNode needs, the load fails if the file is missing any of them
+pub const REQUIRED_COLUMNS: &[&str] = &[
+    "Weight_state",
+    "Total_physical_act_time",
+    "Life_satisfaction",
+    "Gen_health_state",
+    "Total_income",
+    "Food_security",
+    "High_BP",
+    "High_cholesterol",
+    "Diabetic",
+];
+
+//header spellings used by the decoding dictionary and older survey files, mapped to the dictionary names
+const DEFAULT_ALIASES: &[(&str, &str)] = &[
+    ("High_cholestrol", "High_cholesterol"),
+    ("Health_utility_indx", "Health_utility_index"),
+    ("Tobaco_use", "Tobacco_use"),
+    ("weekly_alcohol", "Weekly_alcohol"),
+    ("Cannabies_use", "Cannabis_use"),
+    ("working_status", "Working_status"),
+];
+
+///options of a load: the parse policy, the columns that must be present, the header alias table,
+///the cut points of the activity levels, the code book used to validate cells and the survey weight column
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    pub policy: ParsePolicy,
+    pub required: Vec<&'static str>,
+    pub aliases: HashMap<String, String>, //header name in the file -> dictionary column name
+    pub activity: ActivityThresholds,
+    pub codebook: CodeBook,
+    pub weight_column: Option<String>, //header of the survey weights(WTS_M in the CCHS files), not in the dictionary
+}
+
+impl LoadOptions {
+    /// Creates the default options(required PersonNode columns, default aliases, bundled code book) with the given policy.
+    pub fn with_policy(policy: ParsePolicy) -> Self {
+        Self {
+            policy,
+            required: REQUIRED_COLUMNS.to_vec(),
+            aliases: DEFAULT_ALIASES.iter().map(|(alias, name)| (alias.to_string(), name.to_string())).collect(),
+            activity: ActivityThresholds::default(),
+            codebook: CodeBook::bundled(),
+            weight_column: None,
+        }
+    }
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self::with_policy(ParsePolicy::CoerceToMissing)
+    }
+}
+
+///a required column that could not be found in the header, with the closest header name if there is one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingColumn {
+    pub column: &'static str,
+    pub suggestion: Option<String>,
+}
+
+///the header row is missing required columns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderError {
+    pub missing: Vec<MissingColumn>,
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "missing required columns:")?;
+        for missing in &self.missing {
+            write!(f, " {}", missing.column)?;
+            if let Some(suggestion) = &missing.suggestion {
+                write!(f, " (did you mean {:?}?)", suggestion)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Error for HeaderError {}
+
+//computes the number of single character edits between two strings, ignoring case
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect(); //distances for the previous row
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            current[j] = (previous[j] + 1).min(current[j - 1] + 1).min(previous[j - 1] + cost);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Maps every schema column to its index in the header, using the alias table for renamed columns.
+/// Returns a HeaderError listing the required columns that are missing, with the closest
+/// unused header name as a suggestion when it is within a few edits.
+pub fn resolve_headers(headers: &StringRecord, options: &LoadOptions) -> Result<HashMap<&'static str, usize>, HeaderError> {
+    let mut positions = HashMap::new();
+    for (index, header) in headers.iter().enumerate() {
+        let header = header.trim();
+        let name = options.aliases.get(header).map_or(header, |name| name.as_str()); //apply the alias table
+        if let Some(column) = COLUMNS.iter().find(|c| c.name == name) {
+            positions.entry(column.name).or_insert(index); //the first matching header wins
+        }
+    }
+    let missing: Vec<MissingColumn> = options.required.iter()
+        .filter(|name| !positions.contains_key(*name))
+        .map(|&column| {
+            let suggestion = headers.iter()
+                .enumerate()
+                .filter(|(index, _)| !positions.values().any(|i| i == index)) //only headers that were not matched
+                .map(|(_, header)| (edit_distance(column, header), header))
+                .filter(|&(distance, _)| distance <= 3)
+                .min_by_key(|&(distance, _)| distance)
+                .map(|(_, header)| header.to_string());
+            MissingColumn { column, suggestion }
+        })
+        .collect();
+    if missing.is_empty() {
+        Ok(positions)
+    } else {
+        Err(HeaderError { missing })
+    }
+}
+
+/// Inputs a CSV file and parses every documented column into a vector of HealthRecord,
+/// checking the header up front and each cell against the schema, handling invalid cells with the options' policy.
+/// Returns the records and a ValidationReport, or an error if the file cannot be read,
+/// a required column is missing(HeaderError) or a cell is invalid with the FailFast policy(ParseIssue).
+/// Optional columns missing from the header are left empty and not validated.
+/// A weight column must be in the header, a weight that is not a number is left empty.
+pub fn load_records_with(path: &str, options: &LoadOptions) -> Result<(Vec<HealthRecord>, ValidationReport), Box<dyn Error>> {
+    let policy = options.policy;
     let file = File::open(path)?; //open the file
     let mut rdr = ReaderBuilder::new()
         .has_headers(true)
         .from_reader(BufReader::new(file)); //create a buffered reader(with headers)
 
     let headers = rdr.headers()?.clone();  //get the headers of the CSV file
-    let mut people = Vec::new(); //create a vector to store the PersonNode instances
+    let positions = resolve_headers(&headers, options)?; //find the index of every known column
+    let weight_position = match &options.weight_column {
+        Some(name) => match headers.iter().position(|header| header.trim() == name) {
+            Some(index) => Some(index),
+            None => return Err(format!("weight column {:?} is not in the header", name).into()),
+        },
+        None => None,
+    };
+    let mut records = Vec::new(); //create a vector to store the HealthRecord instances
+    let mut report = ValidationReport::default();
 
     for (idx, result) in rdr.records().enumerate() { //iterate over the records
         let record = result?;
-        //attempts to parse the CSV record into a PersonNode
-        let get = |field: &str| -> &str { //get the value of the field
-            match headers.iter().position(|h| h == field) { //find the index of the field
-                Some(index) => record.get(index).unwrap_or(""), //get the value of the field
-                None => "", //if the field is not found, return an empty string
-            }
+        report.rows_read += 1;
+        //get the value of the field, or None if the column is not in the file
+        let cell = |field: &str| -> Option<&str> {
+            positions.get(field).map(|&index| record.get(index).unwrap_or(""))
         };
-
-        let weight_state: u8 = get("Weight_state").parse().unwrap_or(9); //BMI category
-        let total_physical_act_time: u16 = get("Total_physical_act_time").parse().unwrap_or(60000); //Physical activity time
-        let life_satisfaction: u8 = get("Life_satisfaction").parse().unwrap_or(99); //Life satisfaction(1-10)
-        let gen_health_state: u8 = get("Gen_health_state").parse().unwrap_or(9); //General health state(1-5)
-        let total_income: u8 = get("Total_income").parse().unwrap_or(9); //Total income(1-9)
-        let food_security: u8 = get("Food_security").parse().unwrap_or(9); //Food security(1-9)
-        let high_bp: bool = get("High_BP") == "1"; //High blood pressure(1=yes, 0=no)
-        let high_cholesterol: bool = get("High_cholestrol") == "1"; //High cholesterol(1=yes, 0=no)
-        let diabetic: bool = get("Diabetic") == "1"; //Diabetic(1=yes, 0=no)
-        // Determine activity level(low, medium, or high) based on total physical activity time
-        let activity_level = match total_physical_act_time {
-            0..=149 => ActivityLevel::Low, 
-            150..=299 => ActivityLevel::Medium,
-            300..=u16::MAX => ActivityLevel::High,
+        //validate every column present in the file
+        let mut rejected = Vec::new();
+        for column in COLUMNS {
+            if let Some(raw) = cell(column.name) {
+                if let Some(reason) = check_cell(column, raw, &options.codebook) {
+                    let issue = ParseIssue { row: idx, column: column.name, raw: raw.to_string(), reason };
+                    if policy == ParsePolicy::FailFast {
+                        return Err(Box::new(issue));
+                    }
+                    rejected.push(column.name);
+                    report.issues.push(issue);
+                }
+            }
+        }
+        if policy == ParsePolicy::SkipRow && !rejected.is_empty() {
+            continue; //drop the row
+        }
+        //attempts to parse the CSV record into a HealthRecord, rejected cells become missing
+        let get = |field: &'static str| -> Response<u32> {
+            if rejected.contains(&field) {
+                Response::Missing(Missing::Blank)
+            } else {
+                options.codebook.response(field, cell(field).unwrap_or(""))
+            }
         };
-        // Create a new PersonNode with the parsed data
-        people.push(PersonNode {
-            id: idx,
-            weight_state,
-            activity_level,
-            life_satisfaction,
-            gen_health_state,
-            total_income,
-            food_security,
-            high_bp,
-            high_cholesterol,
-            diabetic,
-        });
+        let adm_rno1 = cell("ADM_RNO1").unwrap_or("").trim().to_string();
+        let mut health_record = HealthRecord::from_row(adm_rno1, get);
+        //"inf" and "NaN" parse as f64 but are no survey weight
+        health_record.weight = weight_position.and_then(|index| record.get(index)?.trim().parse().ok()).filter(|w: &f64| w.is_finite());
+        records.push(health_record);
+        report.rows_kept += 1;
     }
 
+    Ok((records, report))
+}
+
+/// Inputs a CSV file and parses it into a vector of PersonNode.
+/// Returns a vector of PersonNode or an error if the file cannot be read.
+/// Every column of the file stays available through the `record` field of each PersonNode.
+pub fn load_people(path: &str) -> Result<Vec<PersonNode>, Box<dyn Error>> {
+    let (people, _) = load_people_with(path, &LoadOptions::default())?;
     Ok(people)
-}
\ No newline at end of file
+}
+
+/// Inputs a CSV file and parses it into a vector of PersonNode using the given LoadOptions.
+/// Returns the people and the ValidationReport of the load.
+pub fn load_people_with(path: &str, options: &LoadOptions) -> Result<(Vec<PersonNode>, ValidationReport), Box<dyn Error>> {
+    let (records, report) = load_records_with(path, options)?;
+    let people = records.into_iter().enumerate().map(|(idx, record)| person_from_record(idx, record, &options.activity)).collect();
+    Ok((people, report))
+}
+
+/// Builds the PersonNode used by the graph from a full HealthRecord.
+/// Reserve codes and invalid values stay missing, with the reason given by the record.
+/// The activity level is derived with the given thresholds.
+pub fn person_from_record(idx: usize, record: HealthRecord, activity: &ActivityThresholds) -> PersonNode {
+    //converts a numeric code to u8, a value that does not fit is treated as blank
+    let code = |value: Response<u32>| -> Response<u8> {
+        match value {
+            Response::Valid(v) => u8::try_from(v).map_or(Response::Missing(Missing::Blank), Response::Valid),
+            Response::Missing(reason) => Response::Missing(reason),
+        }
+    };
+    //converts a yes/no answer(1=yes, 2=no)
+    let yes = |value: Response<u32>| -> Response<bool> { value.map(|v| v == 1) };
+    let weight_state = code(record.weight_state); //BMI category
+    let life_satisfaction = code(record.life_satisfaction); //Life satisfaction(0-10)
+    let gen_health_state = code(record.gen_health_state); //General health state(1-5)
+    let total_income = code(record.total_income); //Total income(1-5)
+    let food_security = code(record.food_security); //Food security(0-3)
+    let high_bp = yes(record.high_bp); //High blood pressure
+    let high_cholesterol = yes(record.high_cholesterol); //High cholesterol
+    let diabetic = yes(record.diabetic); //Diabetic
+    // Determine activity level(low, medium, high or unknown) based on total physical activity time
+    let activity_level = ActivityLevel::from_minutes(record.total_physical_act_time, record.physical_vigorous_act_time, activity);
+    // Create a new PersonNode with the parsed data
+    PersonNode {
+        id: idx,
+        weight_state,
+        activity_level,
+        life_satisfaction,
+        gen_health_state,
+        total_income,
+        food_security,
+        high_bp,
+        high_cholesterol,
+        diabetic,
+        record,
+    }
+}
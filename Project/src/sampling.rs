@@ -0,0 +1,134 @@
+//This module selects the people of the graph from the loaded dataset.
+//A sample can be uniform, stratified by a categorical column(province, income, age group, ...)
+//and can respect the survey weights of the respondents.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use crate::graph::PersonNode;
+use crate::schema::{column, ColumnKind};
+
+//how the sample size is shared between the strata
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Allocation {
+    #[default]
+    Proportional, //each stratum gets a share proportional to its size in the dataset
+    Equal,        //each stratum gets the same share, small strata give their unused share to the others
+}
+
+///how a sample is drawn: the column it is stratified by, how the strata share the sample
+///and whether the survey weights are used
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SampleDesign {
+    pub stratify_by: Option<String>, //dictionary name of a categorical column, None for a single stratum
+    pub allocation: Allocation,
+    pub weighted: bool, //draw people with a probability proportional to their survey weight
+}
+
+///a sample design that cannot be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SamplingError {
+    UnknownColumn(String),  //the stratification column is not in the schema
+    NotCategorical(String), //the stratification column holds a record number, counts or minutes
+}
+
+impl fmt::Display for SamplingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SamplingError::UnknownColumn(name) => write!(f, "cannot stratify by {:?}: unknown column", name),
+            SamplingError::NotCategorical(name) => write!(f, "cannot stratify by {:?}: not a categorical column", name),
+        }
+    }
+}
+
+impl Error for SamplingError {}
+
+/// Draws a sample of at most `size` people with the given design and random number generator.
+/// Missing answers of the stratification column form one stratum of their own.
+/// With a weighted design, people without a positive finite survey weight are never selected.
+/// The same people, design and RNG state always give the same sample.
+pub fn draw(people: Vec<PersonNode>, size: usize, design: &SampleDesign, rng: &mut impl Rng) -> Result<Vec<PersonNode>, SamplingError> {
+    let people: Vec<PersonNode> = if design.weighted {
+        people.into_iter().filter(|p| p.record.weight.is_some_and(|w| w.is_finite() && w > 0.0)).collect()
+    } else {
+        people
+    };
+    let Some(name) = &design.stratify_by else {
+        return Ok(draw_stratum(people, size, design.weighted, rng));
+    };
+    let column = column(name).ok_or_else(|| SamplingError::UnknownColumn(name.clone()))?;
+    if matches!(column.kind, ColumnKind::Id | ColumnKind::Continuous) {
+        return Err(SamplingError::NotCategorical(name.clone()));
+    }
+    //group the people by their answer, a BTreeMap keeps the strata in the same order on every run
+    let mut strata: BTreeMap<Option<u32>, Vec<PersonNode>> = BTreeMap::new();
+    for person in people {
+        let key = person.record.get(column.name).and_then(|answer| answer.value());
+        strata.entry(key).or_default().push(person);
+    }
+    let counts: Vec<usize> = strata.values().map(|stratum| stratum.len()).collect();
+    let shares = allocate(&counts, size, design.allocation);
+    let mut sample = Vec::with_capacity(shares.iter().sum());
+    for (stratum, share) in strata.into_values().zip(shares) {
+        sample.extend(draw_stratum(stratum, share, design.weighted, rng));
+    }
+    Ok(sample)
+}
+
+/// Shares a sample size between strata of the given sizes, no stratum gets more people than it has.
+/// Proportional shares are rounded with the largest remainder method so they add up to the sample size.
+pub fn allocate(counts: &[usize], size: usize, allocation: Allocation) -> Vec<usize> {
+    let total: usize = counts.iter().sum();
+    let size = size.min(total);
+    if total == 0 {
+        return vec![0; counts.len()];
+    }
+    match allocation {
+        Allocation::Proportional => {
+            let mut shares: Vec<usize> = counts.iter().map(|&count| count * size / total).collect();
+            //give the people lost by rounding down to the strata with the largest remainders
+            let mut order: Vec<usize> = (0..counts.len()).collect();
+            order.sort_by_key(|&i| std::cmp::Reverse(counts[i] * size % total));
+            let left = size - shares.iter().sum::<usize>();
+            for &i in order.iter().take(left) {
+                shares[i] += 1;
+            }
+            shares
+        }
+        Allocation::Equal => {
+            //hand out one person at a time to every stratum that still has people
+            let mut shares = vec![0; counts.len()];
+            let mut left = size;
+            while left > 0 {
+                for (share, &count) in shares.iter_mut().zip(counts) {
+                    if left > 0 && *share < count {
+                        *share += 1;
+                        left -= 1;
+                    }
+                }
+            }
+            shares
+        }
+    }
+}
+
+//draws `size` people of one stratum, uniformly or by weight
+fn draw_stratum(mut people: Vec<PersonNode>, size: usize, weighted: bool, rng: &mut impl Rng) -> Vec<PersonNode> {
+    if !weighted {
+        people.shuffle(rng);
+        people.truncate(size);
+        return people;
+    }
+    //weighted sampling without replacement(Efraimidis and Spirakis): each person gets the key ln(u)/w
+    //for a uniform u in (0, 1] and the people with the largest keys are selected
+    let mut keyed: Vec<(f64, PersonNode)> = people.into_iter()
+        .map(|person| {
+            let u: f64 = 1.0 - rng.gen::<f64>(); //gen is in [0, 1)
+            (u.ln() / person.record.weight.unwrap_or(1.0), person)
+        })
+        .collect();
+    keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+    keyed.into_iter().take(size).map(|(_, person)| person).collect()
+}
@@ -0,0 +1,172 @@
+//This module defines the column schema of the CCHS dataset and the full typed record built from it.
+//The column list follows Projectdata/Data_dictionary.txt, so adding a column only means adding a line below.
+//Valid codes, labels and reserve codes of each column are in the code book(see codebook.rs).
+
+use serde::Deserialize;
+
+//describes how the values of a column should be interpreted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Id,         //unique record number, kept as text
+    Nominal,    //unordered categories(province, gender, ...)
+    Ordinal,    //ordered categories(health state, income, ...)
+    Binary,     //yes/no questions
+    Continuous, //counts and minutes
+}
+
+//why a value is missing, following the CCHS reserve codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Missing {
+    Blank,         //the cell was empty, not a number or rejected by validation
+    NotApplicable, //valid skip: the question was not asked
+    DontKnow,
+    Refused,
+    NotStated,
+}
+
+///a survey answer that is either a valid value or missing for a known reason
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Response<T> {
+    Valid(T),
+    Missing(Missing),
+}
+
+impl<T: Copy> Response<T> {
+    /// Returns the value if the answer is valid.
+    pub fn value(self) -> Option<T> {
+        match self {
+            Response::Valid(value) => Some(value),
+            Response::Missing(_) => None,
+        }
+    }
+    /// Returns true if the answer is valid.
+    pub fn is_valid(self) -> bool {
+        matches!(self, Response::Valid(_))
+    }
+    /// Converts a valid value and keeps the reason of a missing one.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Response<U> {
+        match self {
+            Response::Valid(value) => Response::Valid(f(value)),
+            Response::Missing(reason) => Response::Missing(reason),
+        }
+    }
+}
+
+impl<T> Default for Response<T> {
+    fn default() -> Self {
+        Response::Missing(Missing::Blank)
+    }
+}
+
+//a single documented column of the dataset
+#[derive(Debug, Clone, Copy)]
+pub struct Column {
+    pub name: &'static str,
+    pub kind: ColumnKind,
+}
+
+/// Generates the `HealthRecord` struct and the `COLUMNS` table from one list of columns.
+/// Every column other than ADM_RNO1 holds a numeric code, so its field is a `Response<u32>`
+/// which is missing when the cell is a reserve code, empty or not a number.
+macro_rules! health_record {
+    ($($field:ident => $name:literal, $kind:ident;)*) => {
+        ///every documented CCHS column for one respondent
+        #[derive(Debug, Clone, Default, PartialEq)]
+        pub struct HealthRecord {
+            pub adm_rno1: String,
+            $(pub $field: Response<u32>,)*
+            pub weight: Option<f64>, //survey weight, read from the column named by LoadOptions::weight_column
+        }
+
+        ///all columns of the dataset in dictionary order
+        pub const COLUMNS: &[Column] = &[
+            Column { name: "ADM_RNO1", kind: ColumnKind::Id },
+            $(Column { name: $name, kind: ColumnKind::$kind },)*
+        ];
+
+        impl HealthRecord {
+            /// Builds a record from its record number and a lookup function that returns the answer for a column name.
+            pub fn from_row(adm_rno1: String, get: impl Fn(&'static str) -> Response<u32>) -> Self {
+                Self {
+                    adm_rno1,
+                    $($field: get($name),)*
+                    weight: None,
+                }
+            }
+            /// Returns the answer of a column by its dictionary name.
+            /// Returns None for unknown columns and ADM_RNO1.
+            pub fn get(&self, column: &str) -> Option<Response<u32>> {
+                match column {
+                    $($name => Some(self.$field),)*
+                    _ => None,
+                }
+            }
+            /// Returns a function reading the answer of a column, for loops that read the same column of many records.
+            /// Returns None for unknown columns and ADM_RNO1.
+            pub fn accessor(column: &str) -> Option<fn(&HealthRecord) -> Response<u32>> {
+                match column {
+                    $($name => Some((|record: &HealthRecord| record.$field) as fn(&HealthRecord) -> Response<u32>),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+health_record! {
+    province => "Province", Nominal;
+    health_region_grouped => "Health_region_grouped", Nominal;
+    gender => "Gender", Nominal;
+    marital_status => "Marital_status", Nominal;
+    household => "Household", Nominal;
+    age => "Age", Ordinal;
+    worked_job_business => "Worked_job_business", Binary;
+    edu_level => "Edu_level", Ordinal;
+    gen_health_state => "Gen_health_state", Ordinal;
+    life_satisfaction => "Life_satisfaction", Ordinal;
+    mental_health_state => "Mental_health_state", Ordinal;
+    stress_level => "Stress_level", Ordinal;
+    work_stress => "Work_stress", Ordinal;
+    sense_belonging => "Sense_belonging", Ordinal;
+    weight_state => "Weight_state", Nominal;
+    bmi_12_17 => "BMI_12_17", Ordinal;
+    bmi_18_above => "BMI_18_above", Ordinal;
+    sleep_apnea => "Sleep_apnea", Binary;
+    high_bp => "High_BP", Binary;
+    high_cholesterol => "High_cholesterol", Binary;
+    diabetic => "Diabetic", Binary;
+    fatigue_syndrome => "Fatigue_syndrome", Binary;
+    mood_disorder => "Mood_disorder", Binary;
+    anxiety_disorder => "Anxiety_disorder", Binary;
+    respiratory_chronic_con => "Respiratory_chronic_con", Binary;
+    musculoskeletal_con => "Musculoskeletal_con", Binary;
+    cardiovascular_con => "Cardiovascular_con", Binary;
+    health_utility_index => "Health_utility_index", Binary;
+    pain_status => "Pain_status", Binary;
+    act_improve_health => "Act_improve_health", Binary;
+    fruit_veg_con => "Fruit_veg_con", Ordinal;
+    smoked => "Smoked", Continuous;
+    tobacco_use => "Tobacco_use", Binary;
+    weekly_alcohol => "Weekly_alcohol", Binary;
+    cannabis_use => "Cannabis_use", Nominal;
+    drug_use => "Drug_use", Binary;
+    total_active_time => "Total_active_time", Continuous;
+    total_physical_act_time => "Total_physical_act_time", Continuous;
+    other_physical_act_time => "Other_physical_act_time", Continuous;
+    physical_vigorous_act_time => "Physical_vigorous_act_time", Continuous;
+    work_hours => "Work_hours", Continuous;
+    working_status => "Working_status", Nominal;
+    aboriginal_identity => "Aboriginal_identity", Binary;
+    birth_country => "Birth_country", Nominal;
+    immigrant => "Immigrant", Binary;
+    insurance_cover => "Insurance_cover", Binary;
+    food_security => "Food_security", Ordinal;
+    income_source => "Income_source", Nominal;
+    total_income => "Total_income", Ordinal;
+}
+
+/// Finds the schema entry of a column by its dictionary name.
+pub fn column(name: &str) -> Option<&'static Column> {
+    COLUMNS.iter().find(|c| c.name == name)
+}
@@ -1,12 +1,20 @@
 // this module defines the similarity function to check if two PersonNode instances are similar
+//and the graded similarity score used to weight the edges of the graph
+//both can be tuned with a SimilarityConfig profile loaded from TOML(see similarity.toml for the default profile)
 
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::sync::LazyLock;
+use serde::Deserialize;
 use crate::graph::{PersonNode, ActivityLevel};
+use crate::schema::{Missing, Response};
 
 /// Check if two PersonNode instances are similar based on various health and lifestyle attributes.
 /// Inputs two PersonNode structs
 /// Returns true if they are similar, false otherwise.
 /// The similarity is determined by comparing their weight state, activity level, life satisfaction, health state, income, food security, and health conditions.
-
+/// Health conditions must all be the same, over the chronic conditions both people answered(see CHRONIC_CONDITIONS).
 pub fn is_similar(p1: &PersonNode, p2: &PersonNode) -> bool {
     same_weight(p1, p2) &&
     same_activity_level(p1, p2) &&
@@ -17,36 +25,302 @@ pub fn is_similar(p1: &PersonNode, p2: &PersonNode) -> bool {
     same_health_conditions(p1, p2)
 }
 
-//returns true if weight state is the same and not unknown
+//the default profile, built once since similarity_score is called for every pair of people
+static DEFAULT_PROFILE: LazyLock<SimilarityConfig> = LazyLock::new(SimilarityConfig::default);
+
+/// Computes a graded similarity of two people in [0, 1], the mean of their component scores.
+/// A missing answer scores 0, like it fails is_similar.
+/// Unlike is_similar, two people one income bracket apart are still close.
+/// This is similarity_score_with the default profile(similarity.toml), so passing that profile changes nothing.
+pub fn similarity_score(p1: &PersonNode, p2: &PersonNode) -> f64 {
+    similarity_score_with(p1, p2, &DEFAULT_PROFILE)
+}
+
+//ranks the activity levels from Low(0) to High(2), Unknown is missing
+fn activity_rank(level: &ActivityLevel) -> Response<u8> {
+    match level {
+        ActivityLevel::Low => Response::Valid(0),
+        ActivityLevel::Medium => Response::Valid(1),
+        ActivityLevel::High => Response::Valid(2),
+        ActivityLevel::Unknown => Response::Missing(Missing::Blank),
+    }
+}
+//returns both values if both answers are valid, a missing answer never matches
+fn both_valid<T: Copy>(a: Response<T>, b: Response<T>) -> Option<(T, T)> {
+    Some((a.value()?, b.value()?))
+}
+//returns true if weight state is the same and not missing
 fn same_weight(p1: &PersonNode, p2: &PersonNode) -> bool {
-    p1.weight_state == p2.weight_state && p1.weight_state != 9
+    both_valid(p1.weight_state, p2.weight_state).is_some_and(|(a, b)| a == b)
 }
 //returns true if activity level is the same and not unknown
 fn same_activity_level(p1: &PersonNode, p2: &PersonNode) -> bool {
     p1.activity_level == p2.activity_level && p1.activity_level != ActivityLevel::Unknown
 }
+//returns true if life satisfaction is within 1 point of each other and not missing
 fn close_life_satisfaction(p1: &PersonNode, p2: &PersonNode) -> bool {
-    // Check if life satisfaction is within 1 point of each other and not greater than 10
-    let diff = (p1.life_satisfaction as i8 - p2.life_satisfaction as i8).abs(); // calculate the difference
-    diff <= 1 && p1.life_satisfaction <= 10 && p2.life_satisfaction <= 10
+    both_valid(p1.life_satisfaction, p2.life_satisfaction).is_some_and(|(a, b)| a.abs_diff(b) <= 1)
 }
-//returns true if health state is within 1 point of each other and not greater than 5
+//returns true if health state is within 1 point of each other and not missing
 fn close_health_state(p1: &PersonNode, p2: &PersonNode) -> bool {
-    let diff = (p1.gen_health_state as i8 - p2.gen_health_state as i8).abs(); // calculate the difference
-    diff <= 1 && p1.gen_health_state <= 5 && p2.gen_health_state <= 5
+    both_valid(p1.gen_health_state, p2.gen_health_state).is_some_and(|(a, b)| a.abs_diff(b) <= 1)
 }
-//returns true if income is the same and not 9
+//returns true if income is the same and not missing
 fn same_income(p1: &PersonNode, p2: &PersonNode) -> bool {
-    p1.total_income == p2.total_income && p1.total_income != 9
+    both_valid(p1.total_income, p2.total_income).is_some_and(|(a, b)| a == b)
 }
-//returns true if food security is the same and not 9
+//returns true if food security is the same and not missing
 fn same_food_security(p1: &PersonNode, p2: &PersonNode) -> bool {
-    p1.food_security == p2.food_security && p1.food_security != 9
+    both_valid(p1.food_security, p2.food_security).is_some_and(|(a, b)| a == b)
 }
-//returns true if at least one health condition matches
+//returns true if every chronic condition answered by both is the same, false if none was answered by both
 fn same_health_conditions(p1: &PersonNode, p2: &PersonNode) -> bool {
-    // at least one health condition must match
-    (p1.high_bp == p2.high_bp) ||
-    (p1.high_cholesterol == p2.high_cholesterol) ||
-    (p1.diabetic == p2.diabetic)
-}
\ No newline at end of file
+    same_conditions(p1, p2, ConditionMatch::AllEqual, 0).unwrap_or(false)
+}
+
+///the chronic conditions of the dictionary, compared by the health condition rules
+pub const CHRONIC_CONDITIONS: &[&str] = &[
+    "Sleep_apnea",
+    "High_BP",
+    "High_cholesterol",
+    "Diabetic",
+    "Fatigue_syndrome",
+    "Mood_disorder",
+    "Anxiety_disorder",
+    "Respiratory_chronic_con",
+    "Musculoskeletal_con",
+    "Cardiovascular_con",
+];
+
+//how the chronic conditions of two people are compared
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConditionMatch {
+    #[default]
+    AllEqual,        //every condition is the same, scores the share of conditions that are the same
+    Jaccard(f64),    //the Jaccard similarity of the diagnosed conditions is at least the value, scores the Jaccard similarity
+    SharedDiagnosis, //at least one condition is diagnosed in both people, scores 1 or 0
+}
+
+//the answers to CHRONIC_CONDITIONS of a person, true for a diagnosis
+//blood pressure, cholesterol and diabetes come from the PersonNode fields, the others from its full record
+fn diagnoses(p: &PersonNode) -> [Response<bool>; 10] {
+    let yes = |value: Response<u32>| value.map(|v| v == 1); //1=yes, 2=no
+    let record = &p.record;
+    [
+        yes(record.sleep_apnea),
+        p.high_bp,
+        p.high_cholesterol,
+        p.diabetic,
+        yes(record.fatigue_syndrome),
+        yes(record.mood_disorder),
+        yes(record.anxiety_disorder),
+        yes(record.respiratory_chronic_con),
+        yes(record.musculoskeletal_con),
+        yes(record.cardiovascular_con),
+    ]
+}
+
+//returns the answers of the conditions both people answered
+fn answered_conditions(p1: &PersonNode, p2: &PersonNode) -> Vec<(bool, bool)> {
+    diagnoses(p1).into_iter().zip(diagnoses(p2)).filter_map(|(a, b)| both_valid(a, b)).collect()
+}
+
+/// Scores how alike the chronic conditions of two people are, in [0, 1], over the conditions both answered.
+/// With Jaccard, two people without any diagnosis score 1.
+/// Returns None if no condition was answered by both.
+pub fn condition_score(p1: &PersonNode, p2: &PersonNode, semantics: ConditionMatch) -> Option<f64> {
+    let answers = answered_conditions(p1, p2);
+    if answers.is_empty() {
+        return None;
+    }
+    let count = |f: fn(&(bool, bool)) -> bool| answers.iter().filter(|pair| f(pair)).count();
+    Some(match semantics {
+        ConditionMatch::AllEqual => count(|(a, b)| a == b) as f64 / answers.len() as f64,
+        ConditionMatch::Jaccard(_) => {
+            let either = count(|(a, b)| *a || *b);
+            if either == 0 { 1.0 } else { count(|(a, b)| *a && *b) as f64 / either as f64 }
+        }
+        ConditionMatch::SharedDiagnosis => if count(|(a, b)| *a && *b) > 0 { 1.0 } else { 0.0 },
+    })
+}
+
+/// Checks if two people have the same chronic conditions with the given semantics, over the conditions both answered.
+/// With AllEqual, up to `tolerance` conditions may differ.
+/// Returns None if no condition was answered by both.
+pub fn same_conditions(p1: &PersonNode, p2: &PersonNode, semantics: ConditionMatch, tolerance: u8) -> Option<bool> {
+    match semantics {
+        ConditionMatch::AllEqual => {
+            let answers = answered_conditions(p1, p2);
+            let differing = answers.iter().filter(|(a, b)| a != b).count();
+            (!answers.is_empty()).then_some(differing <= tolerance as usize)
+        }
+        ConditionMatch::Jaccard(min) => condition_score(p1, p2, semantics).map(|score| score >= min),
+        ConditionMatch::SharedDiagnosis => condition_score(p1, p2, semantics).map(|score| score == 1.0),
+    }
+}
+
+//an attribute of PersonNode that can take part in the similarity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Attribute {
+    WeightState,
+    ActivityLevel,
+    LifeSatisfaction,
+    GenHealthState,
+    TotalIncome,
+    FoodSecurity,
+    HealthConditions, //the chronic conditions, compared as set by SimilarityConfig::conditions
+}
+
+//how a missing answer is compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingRule {
+    #[default]
+    Mismatch, //a missing answer never matches: the rule fails and the component scores 0
+    Match,    //a missing answer always matches: the rule passes and the component scores 1
+    Skip,     //the attribute is not compared: the rule passes and the component is left out of the mean
+}
+
+///how one attribute is compared
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AttributeRule {
+    #[serde(default)]
+    pub tolerance: u8, //largest distance on the scale that still matches, the number of conditions that may differ for health_conditions(all_equal only)
+    #[serde(default = "default_weight")]
+    pub weight: f64, //weight of the component in the similarity score, at least 0
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+///a similarity profile: the attributes that take part, how each one is compared, how the chronic conditions
+///are matched and how missing answers are handled
+///the default profile gives the same results as is_similar
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SimilarityConfig {
+    #[serde(default)]
+    pub missing: MissingRule,
+    #[serde(default)]
+    pub conditions: ConditionMatch,
+    pub attributes: BTreeMap<Attribute, AttributeRule>,
+}
+
+impl Default for SimilarityConfig {
+    fn default() -> Self {
+        let rule = |tolerance| AttributeRule { tolerance, weight: 1.0 };
+        Self {
+            missing: MissingRule::Mismatch,
+            conditions: ConditionMatch::AllEqual,
+            attributes: BTreeMap::from([
+                (Attribute::WeightState, rule(0)),
+                (Attribute::ActivityLevel, rule(0)),
+                (Attribute::LifeSatisfaction, rule(1)),
+                (Attribute::GenHealthState, rule(1)),
+                (Attribute::TotalIncome, rule(0)),
+                (Attribute::FoodSecurity, rule(0)),
+                (Attribute::HealthConditions, rule(0)), //every condition is the same
+            ]),
+        }
+    }
+}
+
+impl SimilarityConfig {
+    /// Loads a profile from a TOML file.
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+    /// Parses a profile from TOML text.
+    /// Fails if a weight is negative or not finite, if no weight is positive or if the Jaccard threshold is not in [0, 1],
+    /// since the similarity score would no longer be in [0, 1].
+    /// Also fails if health_conditions has a tolerance with conditions other than all_equal, which would ignore it.
+    pub fn from_toml(text: &str) -> Result<Self, Box<dyn Error>> {
+        let config: Self = toml::from_str(text)?;
+        config.validate()?;
+        Ok(config)
+    }
+    //checks the weights and the Jaccard threshold of the profile
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        for (attribute, rule) in &self.attributes {
+            if !rule.weight.is_finite() || rule.weight < 0.0 {
+                return Err(format!("attribute {:?}: weight {} is not a finite number of at least 0", attribute, rule.weight).into());
+            }
+        }
+        if !self.attributes.values().any(|rule| rule.weight > 0.0) {
+            return Err("the profile has no attribute with a positive weight".into());
+        }
+        let tolerance = self.attributes.get(&Attribute::HealthConditions).map_or(0, |rule| rule.tolerance);
+        if tolerance != 0 && self.conditions != ConditionMatch::AllEqual { //only all_equal lets conditions differ
+            return Err(format!("health_conditions: tolerance {} only applies to conditions = \"all_equal\"", tolerance).into());
+        }
+        if let ConditionMatch::Jaccard(min) = self.conditions {
+            if !(0.0..=1.0).contains(&min) {
+                return Err(format!("jaccard threshold {} is not in [0, 1]", min).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+//compares the answers of two people for an attribute other than health_conditions:
+//their distance and the width of the scale, or None when an answer is missing
+fn compare(attribute: Attribute, p1: &PersonNode, p2: &PersonNode) -> Option<(u8, u8)> {
+    let on_scale = |a: Response<u8>, b: Response<u8>, width: u8| both_valid(a, b).map(|(a, b)| (a.abs_diff(b).min(width), width));
+    match attribute {
+        Attribute::WeightState => both_valid(p1.weight_state, p2.weight_state).map(|(a, b)| (u8::from(a != b), 1)),
+        Attribute::ActivityLevel => on_scale(activity_rank(&p1.activity_level), activity_rank(&p2.activity_level), 2),
+        Attribute::LifeSatisfaction => on_scale(p1.life_satisfaction, p2.life_satisfaction, 10), //0-10
+        Attribute::GenHealthState => on_scale(p1.gen_health_state, p2.gen_health_state, 4), //1-5
+        Attribute::TotalIncome => on_scale(p1.total_income, p2.total_income, 4), //1-5
+        Attribute::FoodSecurity => on_scale(p1.food_security, p2.food_security, 3), //0-3
+        Attribute::HealthConditions => None, //see condition_score and same_conditions
+    }
+}
+
+/// Checks if two people are similar under a profile: every attribute of the profile must be within its tolerance.
+/// health_conditions is checked with same_conditions and the conditions of the profile.
+pub fn is_similar_with(p1: &PersonNode, p2: &PersonNode, config: &SimilarityConfig) -> bool {
+    config.attributes.iter().all(|(&attribute, rule)| {
+        let matched = match attribute {
+            Attribute::HealthConditions => same_conditions(p1, p2, config.conditions, rule.tolerance),
+            _ => compare(attribute, p1, p2).map(|(distance, _)| distance <= rule.tolerance),
+        };
+        matched.unwrap_or(config.missing != MissingRule::Mismatch) //a missing answer
+    })
+}
+
+/// Scores one attribute of two people under a profile, in [0, 1].
+/// An attribute scores 1 minus its distance over the width of its scale, health_conditions the condition_score of the profile.
+/// Returns None when an answer is missing, whatever the missing rule of the profile.
+pub fn attribute_score(attribute: Attribute, p1: &PersonNode, p2: &PersonNode, config: &SimilarityConfig) -> Option<f64> {
+    match attribute {
+        Attribute::HealthConditions => condition_score(p1, p2, config.conditions),
+        _ => compare(attribute, p1, p2).map(|(distance, width)| 1.0 - distance as f64 / width as f64),
+    }
+}
+
+/// Computes the similarity score of two people under a profile, the weighted mean of its attribute scores in [0, 1](see attribute_score).
+/// Returns 0.0 if no attribute could be compared.
+pub fn similarity_score_with(p1: &PersonNode, p2: &PersonNode, config: &SimilarityConfig) -> f64 {
+    let mut total = 0.0;
+    let mut total_weight = 0.0;
+    for (&attribute, rule) in &config.attributes {
+        let score = match (attribute_score(attribute, p1, p2, config), config.missing) {
+            (Some(score), _) => score,
+            (None, MissingRule::Mismatch) => 0.0,
+            (None, MissingRule::Match) => 1.0,
+            (None, MissingRule::Skip) => continue,
+        };
+        total += rule.weight * score;
+        total_weight += rule.weight;
+    }
+    if total_weight > 0.0 {
+        total / total_weight
+    } else {
+        0.0
+    }
+}
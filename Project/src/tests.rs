@@ -1,9 +1,32 @@
-//This contains the tests for the graph module to check the functionality of the graph and the similarity function
+//This contains the tests of the library: loading, the graph, the similarity rules and the analyses
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
-    use super::super::graph::{HealthGraph, PersonNode, ActivityLevel};
-    use super::super::similarity::is_similar;
+    use crate::graph::{Adjacency, HealthGraph, PersonNode, ActivityLevel, ActivityThresholds, GraphNode};
+    use crate::analysis::{average_shortest_path_length, average_weighted_shortest_path_length, graph_stats, node_w_highest_strength};
+    use crate::analysis::{estimate_average_shortest_path_length, estimated_graph_stats};
+    use crate::analysis::{average_shortest_path_length_by_component, connected_components, distance_stats};
+    use crate::{build, build_graph, build_graph_with, build_scored_graph, build_weighted_graph_with, GraphOptions};
+    use crate::builder::blocking_key;
+    use crate::centrality::{self, top_k};
+    use crate::community::{health_by_community, label_propagation, louvain, modularity};
+    use crate::analysis::HealthRates;
+    use std::collections::HashMap;
+    use crate::csr::CsrGraph;
+    use rand::Rng;
+    use crate::gower::GowerModel;
+    use crate::schema::column;
+    use crate::similarity::{attribute_score, is_similar, is_similar_with, similarity_score, similarity_score_with};
+    use crate::similarity::{Attribute, AttributeRule, MissingRule, SimilarityConfig};
+    use crate::similarity::{condition_score, same_conditions, ConditionMatch};
+    use crate::schema::{ColumnKind, HealthRecord, Missing, COLUMNS};
+    use crate::sampling::{allocate, draw, Allocation, SampleDesign, SamplingError};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use crate::codebook::CodeBook;
+    use crate::schema::Response::{self, Valid};
+    use crate::parser::{load_records_with, resolve_headers, ParsePolicy, IssueReason, LoadOptions};
 
 //Tests adding nodes and an edge to the graph
 //makes sure the connections go both ways and the graph size is correct
@@ -13,27 +36,29 @@ mod tests {
         // Create two PersonNode instances
         let person1 = PersonNode {
             id: 0,
-            weight_state: 1,
+            weight_state: Valid(1),
             activity_level: ActivityLevel::High,
-            life_satisfaction: 8,
-            gen_health_state: 2,
-            total_income: 5,
-            food_security: 1,
-            high_bp: false,
-            high_cholesterol: false,
-            diabetic: false,
+            life_satisfaction: Valid(8),
+            gen_health_state: Valid(2),
+            total_income: Valid(5),
+            food_security: Valid(1),
+            high_bp: Valid(false),
+            high_cholesterol: Valid(false),
+            diabetic: Valid(false),
+            record: HealthRecord::default(),
         };
         let person2 = PersonNode {
             id: 1,
-            weight_state: 1,
+            weight_state: Valid(1),
             activity_level: ActivityLevel::Medium,
-            life_satisfaction: 8,
-            gen_health_state: 2,
-            total_income: 5,
-            food_security: 1,
-            high_bp: true,
-            high_cholesterol: false,
-            diabetic: false,
+            life_satisfaction: Valid(8),
+            gen_health_state: Valid(2),
+            total_income: Valid(5),
+            food_security: Valid(1),
+            high_bp: Valid(true),
+            high_cholesterol: Valid(false),
+            diabetic: Valid(false),
+            record: HealthRecord::default(),
         };
         // Add nodes and an edge
         graph.add_node(person1);
@@ -44,35 +69,37 @@ mod tests {
         assert_eq!(graph.total_edges(), 1);
         let neighbors0 = graph.neighbors(0).unwrap(); //get the neighbors of node 0
         let neighbors1 = graph.neighbors(1).unwrap(); //get the neighbors of node 1
-        assert!(neighbors0.contains(&1)); //check if node 1 is a neighbor of node 0
-        assert!(neighbors1.contains(&0)); //check if node 0 is a neighbor of node 1
+        assert!(neighbors0.contains_key(&1)); //check if node 1 is a neighbor of node 0
+        assert!(neighbors1.contains_key(&0)); //check if node 0 is a neighbor of node 1
     }
     //Tests that two similar people are correctly regarded as similar
     #[test]
     fn test_similarity_pos() {
         let person1 = PersonNode{
             id: 0,
-            weight_state: 1,
+            weight_state: Valid(1),
             activity_level: ActivityLevel::Medium,
-            life_satisfaction: 8,
-            gen_health_state: 2,
-            total_income: 5,
-            food_security: 1,
-            high_bp: false,
-            high_cholesterol: false,
-            diabetic: false,
+            life_satisfaction: Valid(8),
+            gen_health_state: Valid(2),
+            total_income: Valid(5),
+            food_security: Valid(1),
+            high_bp: Valid(false),
+            high_cholesterol: Valid(false),
+            diabetic: Valid(false),
+            record: HealthRecord::default(),
         };
         let person2 = PersonNode{
             id: 1,
-            weight_state: 1,
+            weight_state: Valid(1),
             activity_level: ActivityLevel::Medium,
-            life_satisfaction: 7,
-            gen_health_state: 2,
-            total_income: 5,
-            food_security: 1,
-            high_bp: false,
-            high_cholesterol: false,
-            diabetic: false,
+            life_satisfaction: Valid(7),
+            gen_health_state: Valid(2),
+            total_income: Valid(5),
+            food_security: Valid(1),
+            high_bp: Valid(false),
+            high_cholesterol: Valid(false),
+            diabetic: Valid(false),
+            record: HealthRecord::default(),
         };
         assert!(is_similar(&person1, &person2));
     }
@@ -81,28 +108,779 @@ mod tests {
     fn test_similarity_neg() {
         let person1 = PersonNode{
             id: 0,
-            weight_state: 1,
+            weight_state: Valid(1),
             activity_level: ActivityLevel::Medium,
-            life_satisfaction: 8,
-            gen_health_state: 2,
-            total_income: 5,
-            food_security: 1,
-            high_bp: false,
-            high_cholesterol: false,
-            diabetic: false,
+            life_satisfaction: Valid(8),
+            gen_health_state: Valid(2),
+            total_income: Valid(5),
+            food_security: Valid(1),
+            high_bp: Valid(false),
+            high_cholesterol: Valid(false),
+            diabetic: Valid(false),
+            record: HealthRecord::default(),
         };
         let person2 = PersonNode{
             id: 1,
-            weight_state: 2,
+            weight_state: Valid(2),
             activity_level: ActivityLevel::High,
-            life_satisfaction: 5,
-            gen_health_state: 1,
-            total_income: 1,
-            food_security: 0,
-            high_bp: true,
-            high_cholesterol: true,
-            diabetic: true,
+            life_satisfaction: Valid(5),
+            gen_health_state: Valid(1),
+            total_income: Valid(1),
+            food_security: Valid(0),
+            high_bp: Valid(true),
+            high_cholesterol: Valid(true),
+            diabetic: Valid(true),
+            record: HealthRecord::default(),
         };
         assert!(!is_similar(&person1, &person2));
     }
-}
\ No newline at end of file
+    //Tests that the schema covers every dictionary column and that a row is parsed into the record
+    #[test]
+    fn test_record_from_row() {
+        assert_eq!(COLUMNS.len(), 50);
+        let codebook = CodeBook::bundled();
+        let row = |field: &'static str| -> Response<u32> {
+            let raw = match field {
+                "Province" => "35",
+                "Stress_level" => "4",
+                "Cardiovascular_con" => "1",
+                "Total_income" => "not a number",
+                "Food_security" => "6",
+                _ => "",
+            };
+            codebook.response(field, raw)
+        };
+        let record = HealthRecord::from_row("100042".to_string(), row);
+        assert_eq!(record.adm_rno1, "100042");
+        assert_eq!(record.province, Valid(35));
+        assert_eq!(record.get("Stress_level"), Some(Valid(4)));
+        assert_eq!(record.get("Cardiovascular_con"), Some(Valid(1)));
+        assert_eq!(record.total_income, Response::Missing(Missing::Blank)); //unparsable values are missing
+        assert_eq!(record.food_security, Response::Missing(Missing::NotApplicable)); //reserve codes come from the code book
+        assert_eq!(record.get("Not_a_column"), None);
+    }
+    //Tests the three parse policies on a small CSV file with one bad row
+    #[test]
+    fn test_parse_policies() {
+        let path = std::env::temp_dir().join("health_graph_test_policies.csv");
+        std::fs::write(&path, "ADM_RNO1,Age,Total_income,Life_satisfaction\n\
+            1,2,3,8\n\
+            2,x,3,42\n\
+            3,5,9,99\n").unwrap();
+        let path = path.to_str().unwrap();
+        let options = |policy| LoadOptions { required: Vec::new(), ..LoadOptions::with_policy(policy) };
+        //fail fast returns the first rejected cell
+        let err = load_records_with(path, &options(ParsePolicy::FailFast)).unwrap_err();
+        assert_eq!(err.to_string(), "row 1, column Age: \"x\" is not a number");
+        //skip row drops the bad row and reports both cells
+        let (records, report) = load_records_with(path, &options(ParsePolicy::SkipRow)).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(report.rows_skipped(), 1);
+        assert_eq!(report.issues.len(), 2);
+        assert_eq!(report.issues[1].column, "Life_satisfaction");
+        assert_eq!(report.issues[1].raw, "42");
+        assert_eq!(report.issues[1].reason, IssueReason::OutOfRange);
+        //coerce keeps the row with the bad cells missing, reserve codes are not rejected
+        let (records, report) = load_records_with(path, &options(ParsePolicy::CoerceToMissing)).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(report.issues.len(), 2);
+        assert_eq!(records[1].age, Response::Missing(Missing::Blank));
+        assert_eq!(records[1].total_income, Valid(3));
+        assert_eq!(records[1].life_satisfaction, Response::Missing(Missing::Blank));
+        //reserve codes become their missing reason
+        assert_eq!(records[2].total_income, Response::Missing(Missing::NotStated));
+        assert_eq!(records[2].life_satisfaction, Response::Missing(Missing::NotStated));
+    }
+    //Tests that renamed columns are mapped through the alias table and misspelled ones are reported with a suggestion
+    #[test]
+    fn test_resolve_headers() {
+        let mut options = LoadOptions::default();
+        let headers = csv::StringRecord::from(vec!["Weight_state", "Total_physical_act_time", "Life_satisfaction",
+            "Gen_health_state", "Total_income", "Food_security", "High_BP", "High_cholestrol", "Diabetes"]);
+        let err = resolve_headers(&headers, &options).unwrap_err();
+        assert_eq!(err.missing.len(), 1); //High_cholestrol is a default alias, only Diabetic is missing
+        assert_eq!(err.missing[0].column, "Diabetic");
+        assert_eq!(err.missing[0].suggestion.as_deref(), Some("Diabetes"));
+        options.aliases.insert("Diabetes".to_string(), "Diabetic".to_string());
+        let positions = resolve_headers(&headers, &options).unwrap();
+        assert_eq!(positions["High_cholesterol"], 7);
+        assert_eq!(positions["Diabetic"], 8);
+    }
+    //Tests that a missing answer never makes two people similar
+    #[test]
+    fn test_similarity_missing() {
+        let person1 = PersonNode{
+            id: 0,
+            weight_state: Valid(1),
+            activity_level: ActivityLevel::Medium,
+            life_satisfaction: Valid(8),
+            gen_health_state: Valid(2),
+            total_income: Response::Missing(Missing::NotStated),
+            food_security: Valid(1),
+            high_bp: Valid(false),
+            high_cholesterol: Valid(false),
+            diabetic: Valid(false),
+            record: HealthRecord::default(),
+        };
+        let person2 = PersonNode{ id: 1, ..person1.clone() };
+        assert!(!is_similar(&person1, &person2));
+        let person3 = PersonNode{ id: 2, total_income: Valid(3), ..person1.clone() };
+        let person4 = PersonNode{ id: 3, total_income: Valid(3), ..person1.clone() };
+        assert!(is_similar(&person3, &person4));
+    }
+    //Tests the activity level cut points, the Unknown level and counting vigorous minutes twice
+    #[test]
+    fn test_activity_level() {
+        let mut thresholds = ActivityThresholds::default();
+        let none = Response::Missing(Missing::NotApplicable);
+        assert_eq!(ActivityLevel::from_minutes(Valid(149), none, &thresholds), ActivityLevel::Low);
+        assert_eq!(ActivityLevel::from_minutes(Valid(150), none, &thresholds), ActivityLevel::Medium);
+        assert_eq!(ActivityLevel::from_minutes(Valid(300), none, &thresholds), ActivityLevel::High);
+        assert_eq!(ActivityLevel::from_minutes(Response::Missing(Missing::NotStated), Valid(60), &thresholds), ActivityLevel::Unknown);
+        assert_eq!(ActivityLevel::from_minutes(Valid(120), Valid(60), &thresholds), ActivityLevel::Low);
+        thresholds.count_vigorous_twice = true; //120 + 2 × 60 moderate-equivalent minutes
+        assert_eq!(ActivityLevel::from_minutes(Valid(120), Valid(60), &thresholds), ActivityLevel::Medium);
+        assert_eq!(ActivityLevel::from_minutes(Valid(100), Valid(100), &thresholds), ActivityLevel::High);
+        assert_eq!(ActivityLevel::from_minutes(Valid(u32::MAX), Valid(u32::MAX), &thresholds), ActivityLevel::High); //no overflow
+        thresholds.high = 240;
+        assert_eq!(ActivityLevel::from_minutes(Valid(120), Valid(60), &thresholds), ActivityLevel::High);
+    }
+    //Tests decoding codes into labels and back
+    #[test]
+    fn test_codebook_labels() {
+        let codebook = CodeBook::bundled();
+        assert_eq!(codebook.label("Total_income", 3), Some("$40k-$60k"));
+        assert_eq!(codebook.label("Food_security", 2), Some("Moderately insecure"));
+        assert_eq!(codebook.label("Province", 35), Some("Ontario"));
+        assert_eq!(codebook.label("Total_income", 9), None); //reserve codes have no label
+        assert_eq!(codebook.label_or_code("Total_income", 9), "9");
+        assert_eq!(codebook.label("Gen_health_state", 2), Some("Very good"));
+        assert_eq!(codebook.label("Diabetic", 1), Some("Yes"));
+        assert_eq!(codebook.label("Total_physical_act_time", 120), None);
+    }
+    //Tests loading a code book from TOML and JSON and using it to validate cells
+    #[test]
+    fn test_codebook_file() {
+        let toml = "[Total_income]\nordinal = true\nmissing = { 9 = \"not_stated\" }\n[Total_income.labels]\n1 = \"Low\"\n2 = \"High\"\n";
+        let json = r#"{"Total_income": {"ordinal": true, "missing": {"9": "not_stated"}, "labels": {"1": "Low", "2": "High"}}}"#;
+        let from_toml = CodeBook::from_toml(toml).unwrap();
+        let from_json = CodeBook::from_json(json).unwrap();
+        assert_eq!(from_toml, from_json);
+        assert_eq!(from_toml.label("Total_income", 2), Some("High"));
+        assert!(from_toml.is_ordinal("Total_income"));
+        assert_eq!(from_toml.kind(column("Total_income").unwrap()), ColumnKind::Ordinal);
+        assert_eq!(from_toml.kind(column("Province").unwrap()), ColumnKind::Nominal); //not in the code book, from the schema
+        //the bundled code book agrees with the kinds of the schema
+        let bundled = CodeBook::bundled();
+        for column in COLUMNS {
+            assert_eq!(bundled.kind(column), column.kind, "{}", column.name);
+        }
+        assert!(from_toml.accepts("Total_income", 9));
+        assert!(!from_toml.accepts("Total_income", 3)); //only two income groups in this code book
+        assert!(from_toml.accepts("Age", 42)); //columns without codes are not validated
+        assert_eq!(from_toml.response("Total_income", "9"), Response::Missing(Missing::NotStated));
+        assert!(CodeBook::from_toml("[Total_income.labels]\none = \"Low\"\n").is_err());
+    }
+    //Tests that the same seed always gives the same sample and a different seed another one
+    #[test]
+    fn test_seeded_sample() {
+        let person = PersonNode{
+            id: 0,
+            weight_state: Valid(1),
+            activity_level: ActivityLevel::Medium,
+            life_satisfaction: Valid(8),
+            gen_health_state: Valid(2),
+            total_income: Valid(3),
+            food_security: Valid(1),
+            high_bp: Valid(false),
+            high_cholesterol: Valid(false),
+            diabetic: Valid(false),
+            record: HealthRecord::default(),
+        };
+        let people: Vec<PersonNode> = (0..100).map(|id| PersonNode{ id, ..person.clone() }).collect();
+        let ids = |seed| -> Vec<usize> {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            draw(people.clone(), 10, &SampleDesign::default(), &mut rng).unwrap().iter().map(|p| p.id).collect()
+        };
+        assert_eq!(ids(42), ids(42));
+        assert_ne!(ids(42), ids(43));
+        assert_eq!(ids(42).len(), 10);
+    }
+    //Tests the stratum allocations and stratified and weighted sampling
+    #[test]
+    fn test_sampling() {
+        assert_eq!(allocate(&[50, 30, 20], 10, Allocation::Proportional), vec![5, 3, 2]);
+        assert_eq!(allocate(&[5, 3, 2], 5, Allocation::Proportional), vec![3, 1, 1]); //largest remainders get the rounding
+        assert_eq!(allocate(&[50, 30, 2], 12, Allocation::Equal), vec![5, 5, 2]); //the small stratum gives its share away
+        assert_eq!(allocate(&[3, 4], 100, Allocation::Equal), vec![3, 4]);
+        //80 people in Ontario(35) and 20 in Manitoba(46), the last two Manitobans have a weight and the one before an infinite one
+        let people: Vec<PersonNode> = (0..100).map(|id| {
+            let mut record = HealthRecord { province: Valid(if id < 80 { 35 } else { 46 }), ..HealthRecord::default() };
+            record.weight = match id { 98.. => Some(100.0), 97 => Some(f64::INFINITY), _ => None };
+            PersonNode{
+                id,
+                weight_state: Valid(1),
+                activity_level: ActivityLevel::Medium,
+                life_satisfaction: Valid(8),
+                gen_health_state: Valid(2),
+                total_income: Valid(3),
+                food_security: Valid(1),
+                high_bp: Valid(false),
+                high_cholesterol: Valid(false),
+                diabetic: Valid(false),
+                record,
+            }
+        }).collect();
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let mut design = SampleDesign { stratify_by: Some("Province".to_string()), allocation: Allocation::Equal, weighted: false };
+        let sample = draw(people.clone(), 20, &design, &mut rng).unwrap();
+        assert_eq!(sample.iter().filter(|p| p.record.province == Valid(46)).count(), 10);
+        design.allocation = Allocation::Proportional;
+        let sample = draw(people.clone(), 20, &design, &mut rng).unwrap();
+        assert_eq!(sample.iter().filter(|p| p.record.province == Valid(46)).count(), 4);
+        //people without a finite weight are never drawn by a weighted design
+        let weighted = SampleDesign { weighted: true, ..SampleDesign::default() };
+        let mut ids: Vec<usize> = draw(people.clone(), 20, &weighted, &mut rng).unwrap().iter().map(|p| p.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec![98, 99]);
+        design.stratify_by = Some("Total_active_time".to_string());
+        assert_eq!(draw(people.clone(), 20, &design, &mut rng).unwrap_err(), SamplingError::NotCategorical("Total_active_time".to_string()));
+        design.stratify_by = Some("Provence".to_string());
+        assert!(matches!(draw(people, 20, &design, &mut rng), Err(SamplingError::UnknownColumn(_))));
+    }
+    //a health region with the share of its people in poor health, used to test graphs of other payloads
+    #[derive(Clone)]
+    struct Region {
+        code: u32,
+        poor_health: f64,
+    }
+    impl GraphNode for Region {
+        type Id = u32;
+        fn id(&self) -> u32 {
+            self.code
+        }
+    }
+    //Tests building and analyzing a graph of regions instead of people
+    #[test]
+    fn test_generic_graph() {
+        let regions = vec![
+            Region { code: 1011, poor_health: 0.10 },
+            Region { code: 1012, poor_health: 0.12 },
+            Region { code: 3595, poor_health: 0.13 },
+            Region { code: 5911, poor_health: 0.30 },
+        ];
+        let graph: HealthGraph<Region> = build_graph_with(&regions, |a, b| (a.poor_health - b.poor_health).abs() < 0.025);
+        assert_eq!(graph.total_edges(), 2);
+        assert!(graph.neighbors(1012).unwrap().contains_key(&3595));
+        assert_eq!(graph.degree(5911), 0);
+        let stats = graph_stats(&graph);
+        assert_eq!(stats.highest_degree_node, Some(1012));
+        assert_eq!(stats.highest_degree, 2);
+        assert_eq!(stats.average_shortest_path_length, 4.0 / 3.0);
+    }
+    //Tests edge weights, strength and weighted shortest paths
+    #[test]
+    fn test_weighted_edges() {
+        let mut graph: HealthGraph<Region> = HealthGraph::new();
+        for code in [1, 2, 3] {
+            graph.add_node(Region { code, poor_health: 0.1 });
+        }
+        graph.add_weighted_edge(1, 2, 0.5);
+        graph.add_weighted_edge(2, 3, 0.25);
+        graph.add_weighted_edge(1, 3, 0.1);
+        graph.add_weighted_edge(1, 4, 1.0); //4 is not in the graph
+        for weight in [0.0, -0.5, f64::NAN, f64::INFINITY] { //an edge must have a positive finite length 1/weight
+            graph.add_weighted_edge(2, 2, weight);
+            graph.add_weighted_edge(1, 2, weight);
+        }
+        graph.add_edge(3, 3); //self-loops are never added
+        assert_eq!(graph.total_edges(), 3);
+        assert_eq!(graph.degree(3), 2);
+        assert_eq!(graph.weight(3, 3), None);
+        assert_eq!(graph.weight(2, 1), Some(0.5));
+        assert_eq!(graph.weight(1, 4), None);
+        assert_eq!(graph.strength(2), 0.75);
+        assert_eq!(CsrGraph::from(&graph).strength(2), 0.75); //the frozen graph reads the same weights
+        assert_eq!(node_w_highest_strength(&graph), Some((2, 0.75)));
+        //1 to 3 is shorter through 2(2 + 4) than directly(10)
+        assert_eq!(average_weighted_shortest_path_length(&graph), (2.0 + 4.0 + 6.0) / 3.0);
+        assert_eq!(average_shortest_path_length(&graph), 1.0);
+        graph.add_edge(1, 3); //unweighted edges have a weight of 1
+        assert_eq!(graph.weight(3, 1), Some(1.0));
+        assert_eq!(average_weighted_shortest_path_length(&graph), (2.0 + 1.0 + 3.0) / 3.0);
+        //pairs scoring 0 are not connected, even with a threshold of 0
+        let regions: Vec<Region> = [1, 2, 3].map(|code| Region { code, poor_health: 0.1 * code as f64 }).into();
+        let graph = build_weighted_graph_with(&regions, |a, b| if a.code + b.code == 4 { 0.0 } else { 0.5 }, 0.0);
+        assert_eq!(graph.total_edges(), 2);
+        assert_eq!(graph.weight(1, 3), None);
+    }
+    //Tests the graded similarity score and the graph built from it
+    #[test]
+    fn test_similarity_score() {
+        let person1 = PersonNode{
+            id: 0,
+            weight_state: Valid(1),
+            activity_level: ActivityLevel::Medium,
+            life_satisfaction: Valid(8),
+            gen_health_state: Valid(2),
+            total_income: Valid(5),
+            food_security: Valid(1),
+            high_bp: Valid(false),
+            high_cholesterol: Valid(false),
+            diabetic: Valid(false),
+            record: HealthRecord::default(),
+        };
+        assert_eq!(similarity_score(&person1, &person1.clone()), 1.0);
+        //one income bracket apart is still close, but no longer passes is_similar
+        let person2 = PersonNode{ id: 1, total_income: Valid(4), ..person1.clone() };
+        assert!(!is_similar(&person1, &person2));
+        let default = SimilarityConfig::default();
+        assert_eq!(attribute_score(Attribute::TotalIncome, &person1, &person2, &default), Some(0.75));
+        assert_eq!(similarity_score(&person1, &person2), (6.0 + 0.75) / 7.0);
+        //missing answers score 0, or are left out with the skip rule
+        let person3 = PersonNode{ id: 2, weight_state: Response::Missing(Missing::Refused), activity_level: ActivityLevel::Unknown, ..person1.clone() };
+        assert_eq!(attribute_score(Attribute::WeightState, &person1, &person3, &default), None);
+        assert_eq!(attribute_score(Attribute::ActivityLevel, &person1, &person3, &default), None);
+        assert_eq!(similarity_score(&person1, &person3), 5.0 / 7.0);
+        let skip = SimilarityConfig { missing: MissingRule::Skip, ..SimilarityConfig::default() };
+        assert_eq!(similarity_score_with(&person1, &person3, &skip), 1.0);
+        let person4 = PersonNode{ id: 3, activity_level: ActivityLevel::High, high_bp: Valid(true), diabetic: Valid(true), ..person1.clone() };
+        assert_eq!(attribute_score(Attribute::ActivityLevel, &person1, &person4, &default), Some(0.5));
+        assert_eq!(attribute_score(Attribute::HealthConditions, &person1, &person4, &default), Some(1.0 / 3.0));
+        let graph = build_scored_graph(&[person1, person2, person4], 0.9);
+        assert_eq!(graph.total_edges(), 1);
+        assert_eq!(graph.weight(0, 1), Some((6.0 + 0.75) / 7.0));
+    }
+    //Tests that the default profile matches is_similar and that profiles change the rules and scores
+    #[test]
+    fn test_similarity_config() {
+        let person1 = PersonNode{
+            id: 0,
+            weight_state: Valid(1),
+            activity_level: ActivityLevel::Medium,
+            life_satisfaction: Valid(8),
+            gen_health_state: Valid(2),
+            total_income: Valid(5),
+            food_security: Valid(1),
+            high_bp: Valid(false),
+            high_cholesterol: Valid(false),
+            diabetic: Valid(false),
+            record: HealthRecord::default(),
+        };
+        let others = [
+            PersonNode{ id: 1, life_satisfaction: Valid(7), ..person1.clone() },
+            PersonNode{ id: 2, life_satisfaction: Valid(6), ..person1.clone() },
+            PersonNode{ id: 3, total_income: Response::Missing(Missing::NotStated), ..person1.clone() },
+            PersonNode{ id: 4, high_bp: Valid(true), high_cholesterol: Valid(true), ..person1.clone() },
+            PersonNode{ id: 5, high_bp: Valid(true), high_cholesterol: Valid(true), diabetic: Response::Missing(Missing::DontKnow), ..person1.clone() },
+            PersonNode{ id: 6, activity_level: ActivityLevel::Unknown, ..person1.clone() },
+        ];
+        let default = SimilarityConfig::default();
+        for other in &others {
+            assert_eq!(is_similar_with(&person1, other, &default), is_similar(&person1, other), "person {}", other.id);
+        }
+        let file = SimilarityConfig::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/similarity.toml")).unwrap();
+        assert_eq!(file, default);
+        //a looser profile: life satisfaction within 2 points, income left out and missing answers matching
+        let loose = SimilarityConfig::from_toml("missing = \"match\"\n[attributes.life_satisfaction]\ntolerance = 2\nweight = 3.0\n").unwrap();
+        assert_eq!(loose.attributes.len(), 1);
+        assert!(is_similar_with(&person1, &others[1], &loose));
+        assert!(!is_similar_with(&person1, &PersonNode{ life_satisfaction: Valid(5), ..person1.clone() }, &loose));
+        assert!(is_similar_with(&person1, &PersonNode{ life_satisfaction: Response::Missing(Missing::Refused), ..person1.clone() }, &loose));
+        assert!(SimilarityConfig::from_toml("[attributes.shoe_size]\ntolerance = 1\n").is_err());
+        //weights must be finite and at least 0 with one positive, the Jaccard threshold in [0, 1]
+        assert!(SimilarityConfig::from_toml("[attributes.life_satisfaction]\nweight = 2.0\n[attributes.total_income]\nweight = -1.0\n").is_err());
+        assert!(SimilarityConfig::from_toml("[attributes.total_income]\nweight = inf\n").is_err());
+        assert!(SimilarityConfig::from_toml("[attributes.total_income]\nweight = nan\n").is_err());
+        assert!(SimilarityConfig::from_toml("[attributes.total_income]\nweight = 0.0\n").is_err());
+        assert!(SimilarityConfig::from_toml("[attributes.total_income]\nweight = 0.0\n[attributes.food_security]\n").is_ok());
+        assert!(SimilarityConfig::from_toml("conditions = { jaccard = 1.5 }\n[attributes.health_conditions]\n").is_err());
+        assert!(SimilarityConfig::from_toml("conditions = { jaccard = 0.5 }\n[attributes.health_conditions]\n").is_ok());
+        //a tolerance on health_conditions is only used by all_equal
+        assert!(SimilarityConfig::from_toml("conditions = \"shared_diagnosis\"\n[attributes.health_conditions]\ntolerance = 2\n").is_err());
+        assert!(SimilarityConfig::from_toml("conditions = { jaccard = 0.5 }\n[attributes.health_conditions]\ntolerance = 1\n").is_err());
+        assert!(SimilarityConfig::from_toml("[attributes.health_conditions]\ntolerance = 2\n").is_ok());
+        //scores use the weights and the missing rule, the default profile gives similarity_score
+        let skip = SimilarityConfig { missing: MissingRule::Skip, ..SimilarityConfig::default() };
+        for other in &others {
+            assert_eq!(similarity_score_with(&person1, other, &default), similarity_score(&person1, other), "person {}", other.id);
+        }
+        assert_eq!(similarity_score_with(&person1, &others[1], &default), (6.0 + 0.8) / 7.0);
+        assert!((similarity_score_with(&person1, &others[1], &loose) - 0.8).abs() < 1e-12);
+        let mut weighted = SimilarityConfig::default();
+        weighted.attributes.insert(Attribute::TotalIncome, AttributeRule { tolerance: 0, weight: 4.0 });
+        assert_eq!(similarity_score_with(&person1, &others[2], &weighted), 6.0 / 10.0); //the missing income scores 0
+        assert_eq!(attribute_score(Attribute::TotalIncome, &person1, &others[2], &weighted), None);
+        assert_eq!(similarity_score_with(&person1, &others[2], &skip), 1.0); //the missing income is left out
+    }
+    //Tests the Gower distance on nominal, binary, ordinal and continuous columns with missing answers
+    #[test]
+    fn test_gower_distance() {
+        let record = |province, high_bp, gen_health, minutes| HealthRecord {
+            province: Valid(province),
+            high_bp,
+            gen_health_state: Valid(gen_health),
+            total_physical_act_time: Valid(minutes),
+            ..HealthRecord::default()
+        };
+        let records = [
+            record(35, Valid(1), 1, 0),
+            record(35, Valid(2), 3, 300),
+            record(59, Response::Missing(Missing::NotStated), 5, 600),
+        ];
+        let columns = ["Province", "High_BP", "Gen_health_state", "Total_physical_act_time"].map(|name| column(name).unwrap());
+        let codebook = CodeBook::bundled();
+        let model = GowerModel::fit_columns(&records, columns, &codebook);
+        assert_eq!(model.range("Gen_health_state"), Some(4.0)); //ranges come from the fitted records
+        assert_eq!(model.range("Total_physical_act_time"), Some(600.0));
+        assert_eq!(model.range("Age"), None);
+        assert_eq!(model.distance(&records[0], &records[0]), Some(0.0));
+        //same province, different BP, health 2 of 4 apart, 300 of 600 minutes apart
+        assert_eq!(model.distance(&records[0], &records[1]), Some((0.0 + 1.0 + 0.5 + 0.5) / 4.0));
+        //the missing BP answer is left out
+        assert_eq!(model.distance(&records[0], &records[2]), Some((1.0 + 1.0 + 1.0) / 3.0));
+        assert_eq!(model.similarity(&records[1], &records[2]), 1.0 - (1.0 + 0.5 + 0.5) / 3.0);
+        assert_eq!(model.distance(&records[0], &HealthRecord::default()), None);
+        //fitting on every column skips ADM_RNO1 and leaves columns without answers at range 0
+        let full = GowerModel::fit(&records, &codebook);
+        assert_eq!(full.columns().count(), COLUMNS.len() - 1);
+        assert_eq!(full.range("Age"), Some(0.0));
+        assert_eq!(full.distance(&records[0], &records[1]), model.distance(&records[0], &records[1]));
+        //a code book that drops the ordinal flag of Gen_health_state makes it nominal: 1 and 3 differ fully
+        let mut nominal = codebook.clone();
+        nominal.columns.get_mut("Gen_health_state").unwrap().ordinal = false;
+        let model = GowerModel::fit_columns(&records, columns, &nominal);
+        assert_eq!(model.distance(&records[0], &records[1]), Some((0.0 + 1.0 + 1.0 + 0.5) / 4.0));
+    }
+    //Tests the health condition semantics over every chronic condition
+    #[test]
+    fn test_condition_semantics() {
+        let person = |high_bp, diabetic, mood_disorder| PersonNode{
+            id: 0,
+            weight_state: Valid(1),
+            activity_level: ActivityLevel::Medium,
+            life_satisfaction: Valid(8),
+            gen_health_state: Valid(2),
+            total_income: Valid(5),
+            food_security: Valid(1),
+            high_bp: Valid(high_bp),
+            high_cholesterol: Valid(false),
+            diabetic: Valid(diabetic),
+            record: HealthRecord { mood_disorder: Valid(if mood_disorder { 1 } else { 2 }), ..HealthRecord::default() },
+        };
+        //sharing only "not diabetic" is no longer enough
+        let (p1, p2) = (person(true, false, false), person(false, false, true));
+        assert!(!is_similar(&p1, &p2));
+        assert_eq!(same_conditions(&p1, &p2, ConditionMatch::AllEqual, 0), Some(false));
+        assert_eq!(same_conditions(&p1, &p2, ConditionMatch::AllEqual, 2), Some(true));
+        assert_eq!(condition_score(&p1, &p2, ConditionMatch::AllEqual), Some(0.5)); //BP, cholesterol, diabetes and mood answered
+        assert_eq!(condition_score(&p1, &p2, ConditionMatch::Jaccard(0.5)), Some(0.0));
+        assert_eq!(same_conditions(&p1, &p2, ConditionMatch::SharedDiagnosis, 0), Some(false));
+        //the columns kept only in the record take part
+        assert!(!is_similar(&person(false, false, false), &person(false, false, true)));
+        assert!(is_similar(&person(true, true, true), &person(true, true, true)));
+        //one shared diagnosis of two and three
+        let (p3, p4) = (person(true, true, false), person(true, false, true));
+        assert_eq!(condition_score(&p3, &p4, ConditionMatch::Jaccard(0.5)), Some(1.0 / 3.0));
+        assert_eq!(same_conditions(&p3, &p4, ConditionMatch::Jaccard(0.3), 0), Some(true));
+        assert_eq!(same_conditions(&p3, &p4, ConditionMatch::SharedDiagnosis, 0), Some(true));
+        //two people without any diagnosis are alike for Jaccard but share nothing
+        let healthy = person(false, false, false);
+        assert_eq!(condition_score(&healthy, &healthy, ConditionMatch::Jaccard(1.0)), Some(1.0));
+        assert_eq!(condition_score(&healthy, &healthy, ConditionMatch::SharedDiagnosis), Some(0.0));
+        let unanswered = PersonNode{ high_bp: Response::Missing(Missing::Refused), high_cholesterol: Response::Missing(Missing::Refused), diabetic: Response::Missing(Missing::Refused), record: HealthRecord::default(), ..healthy.clone() };
+        assert_eq!(condition_score(&healthy, &unanswered, ConditionMatch::AllEqual), None);
+        //profiles select the semantics
+        let config = SimilarityConfig::from_toml("conditions = { jaccard = 0.3 }\n[attributes.health_conditions]\n").unwrap();
+        assert_eq!(config.conditions, ConditionMatch::Jaccard(0.3));
+        assert!(is_similar_with(&p3, &p4, &config));
+        let config = SimilarityConfig::from_toml("conditions = \"shared_diagnosis\"\n[attributes.health_conditions]\n").unwrap();
+        assert!(!is_similar_with(&healthy, &healthy, &config));
+        assert_eq!(similarity_score_with(&p3, &p4, &config), 1.0);
+    }
+    //generates people with random answers, a few of them missing
+    fn random_people(count: usize, seed: u64) -> Vec<PersonNode> {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let levels = [ActivityLevel::Low, ActivityLevel::Medium, ActivityLevel::High, ActivityLevel::Unknown];
+        let mut answer = |values: u8| if rng.gen_ratio(1, 20) { Response::Missing(Missing::NotStated) } else { Valid(rng.gen_range(1..=values)) };
+        (0..count).map(|id| PersonNode{
+            id,
+            weight_state: answer(2),
+            activity_level: levels[id % 4].clone(),
+            life_satisfaction: answer(10),
+            gen_health_state: answer(5),
+            total_income: answer(2),
+            food_security: answer(2),
+            high_bp: answer(2).map(|v| v == 1),
+            high_cholesterol: answer(2).map(|v| v == 1),
+            diabetic: answer(2).map(|v| v == 1),
+            record: HealthRecord::default(),
+        }).collect()
+    }
+    //Tests that build picks the builder chosen by the graph options
+    #[test]
+    fn test_build_options() {
+        let people = random_people(200, 12);
+        assert_eq!(build(&people, &GraphOptions::default()).unwrap().edges, build_graph(&people).edges);
+        let scored = GraphOptions { threshold: Some(0.8), ..GraphOptions::default() };
+        assert_eq!(build(&people, &scored).unwrap().edges, build_scored_graph(&people, 0.8).edges);
+        let profile = SimilarityConfig::from_toml("[attributes.life_satisfaction]\ntolerance = 2\n").unwrap();
+        let profiled = GraphOptions { profile: Some(profile.clone()), ..GraphOptions::default() };
+        assert_eq!(build(&people, &profiled).unwrap().edges, build_graph_with(&people, |a, b| is_similar_with(a, b, &profile)).edges);
+        let model = GowerModel::fit(people.iter().map(|person| &person.record), &CodeBook::bundled());
+        let gowered = GraphOptions { gower: Some(model.clone()), ..GraphOptions::default() };
+        assert!(build(&people, &gowered).is_err()); //Gower similarity needs a threshold
+        let gowered = GraphOptions { threshold: Some(0.9), ..gowered };
+        let expected = build_weighted_graph_with(&people, |a, b| model.similarity(&a.record, &b.record), 0.9);
+        assert_eq!(build(&people, &gowered).unwrap().edges, expected.edges);
+    }
+    //Tests that blocking on the exact-match answers gives the same graph as comparing every pair
+    #[test]
+    fn test_blocked_graph() {
+        let people = random_people(400, 1);
+        let blocked = build_graph(&people);
+        let full = build_graph_with(&people, is_similar);
+        assert!(full.total_edges() > 0);
+        assert_eq!(blocked.total_edges(), full.total_edges());
+        assert_eq!(blocked.edges, full.edges);
+        assert_eq!(blocked.nodes.len(), people.len());
+        assert_eq!(blocking_key(&people[3]), None); //Unknown activity
+    }
+    //Tests that the CSR form of a graph has dense indices and gives the same analysis as the HealthGraph
+    #[test]
+    fn test_csr_graph() {
+        let mut graph: HealthGraph<Region> = HealthGraph::new();
+        for code in [40, 10, 30, 20] {
+            graph.add_node(Region { code, poor_health: 0.1 });
+        }
+        graph.add_weighted_edge(10, 20, 0.5);
+        graph.add_weighted_edge(20, 30, 0.25);
+        let csr = CsrGraph::from(&graph);
+        assert_eq!(csr.len(), 4);
+        assert_eq!(csr.index_of(30), Some(2));
+        assert_eq!(csr.index_of(50), None);
+        assert_eq!(csr.id_at(3), 40);
+        assert_eq!(csr.neighbors_at(1), &[0, 2]);
+        assert_eq!(csr.weights_at(1), Some(&[0.5, 0.25][..]));
+        assert!(csr.neighbors_at(3).is_empty());
+        assert_eq!(graph_stats(&csr), graph_stats(&graph));
+        assert_eq!(average_weighted_shortest_path_length(&csr), average_weighted_shortest_path_length(&graph));
+        //a graph of unweighted edges stores no weights
+        let people = random_people(300, 3);
+        let unweighted = build_graph(&people);
+        let csr = CsrGraph::from(&unweighted);
+        assert!(!csr.is_weighted());
+        assert_eq!(graph_stats(&csr), graph_stats(&unweighted));
+        let scored = build_scored_graph(&people, 0.8);
+        let csr = CsrGraph::from(&scored);
+        assert!(csr.is_weighted());
+        //strengths are summed in another order, so they can differ in the last bits
+        let (frozen, stats) = (graph_stats(&csr), graph_stats(&scored));
+        assert!((frozen.average_strength - stats.average_strength).abs() < 1e-9);
+        assert_eq!(frozen.highest_degree_node, stats.highest_degree_node);
+        assert_eq!(frozen.average_shortest_path_length, stats.average_shortest_path_length);
+        assert_eq!(node_w_highest_strength(&csr).map(|(id, _)| id), node_w_highest_strength(&scored).map(|(id, _)| id));
+        //freezing a frozen graph gives the same graph
+        let refrozen = CsrGraph::from_adjacency(&csr);
+        assert!(refrozen.is_weighted());
+        assert_eq!(graph_stats(&refrozen), graph_stats(&csr));
+        assert!((0..csr.len()).all(|i| refrozen.neighbors_at(i) == csr.neighbors_at(i) && refrozen.weights_at(i) == csr.weights_at(i)));
+    }
+    //Tests the average shortest path length estimated from sampled sources against the exact one
+    #[test]
+    fn test_path_estimate() {
+        let graph = CsrGraph::from(&build_scored_graph(&random_people(400, 4), 0.85));
+        let exact = average_shortest_path_length(&graph);
+        //every node as a source gives the exact value
+        let full = estimate_average_shortest_path_length(&graph, 1000, &mut ChaCha8Rng::seed_from_u64(1));
+        assert_eq!(full.sources, 400);
+        assert_eq!((full.mean, full.margin), (exact, 0.0));
+        //the same seed draws the same sources
+        let estimate = estimate_average_shortest_path_length(&graph, 60, &mut ChaCha8Rng::seed_from_u64(2));
+        assert_eq!(estimate, estimate_average_shortest_path_length(&graph, 60, &mut ChaCha8Rng::seed_from_u64(2)));
+        assert!(estimate.margin > 0.0 && estimate.margin < 0.5);
+        let (low, high) = estimate.interval();
+        assert!(low <= exact && exact <= high);
+        let stats = estimated_graph_stats(&graph, 60, &mut ChaCha8Rng::seed_from_u64(2));
+        assert_eq!((stats.average_shortest_path_length, stats.path_length_margin), (estimate.mean, Some(estimate.margin)));
+        assert_eq!(stats.path_sources, Some(60));
+        let exact_stats = graph_stats(&graph);
+        assert_eq!((exact_stats.path_length_margin, exact_stats.path_sources), (None, None));
+        //a single source gives no interval
+        let single = estimate_average_shortest_path_length(&graph, 1, &mut ChaCha8Rng::seed_from_u64(4));
+        assert!(single.margin.is_infinite());
+    }
+    //Tests the connected components and the path lengths inside them
+    #[test]
+    fn test_components() {
+        let mut graph: HealthGraph<Region> = HealthGraph::new();
+        for code in 1..=8 {
+            graph.add_node(Region { code, poor_health: 0.1 });
+        }
+        //a path 5-6-7-8, a pair 1-2 and two isolated nodes 3 and 4
+        graph.add_edge(5, 6);
+        graph.add_edge(6, 7);
+        graph.add_edge(7, 8);
+        graph.add_edge(1, 2);
+        let components = connected_components(&graph);
+        assert_eq!(components.count(), 4);
+        assert_eq!(components.sizes, vec![4, 2, 1, 1]);
+        assert_eq!(components.component(8), Some(0));
+        assert_eq!(components.component(2), Some(1));
+        assert_eq!((components.component(3), components.component(4)), (Some(2), Some(3))); //ties go to the smallest id
+        assert_eq!(components.component(9), None);
+        assert_eq!(components.giant_fraction(), 0.5);
+        assert_eq!(components.size_distribution().into_iter().collect::<Vec<_>>(), vec![(1, 2), (2, 1), (4, 1)]);
+        assert_eq!(components.unreachable_pairs(), 28 - 6 - 1);
+        //path 5-6-7-8: 3 pairs at 1, 2 at 2 and 1 at 3
+        assert_eq!(average_shortest_path_length_by_component(&graph, &components), vec![10.0 / 6.0, 1.0, 0.0, 0.0]);
+        let stats = graph_stats(&graph);
+        assert_eq!((stats.components, stats.giant_component_fraction, stats.unreachable_pairs), (4, 0.5, 21));
+        assert_eq!(stats.average_shortest_path_length, (10.0 + 1.0) / 7.0); //only the connected pairs
+        assert_eq!(connected_components(&HealthGraph::<Region>::new()).giant_fraction(), 0.0);
+    }
+    //Tests the eccentricities, diameter, radius, center, periphery and distance histogram
+    #[test]
+    fn test_distance_stats() {
+        let mut graph: HealthGraph<Region> = HealthGraph::new();
+        for code in 1..=7 {
+            graph.add_node(Region { code, poor_health: 0.1 });
+        }
+        //a path 1-2-3-4-5, a pair 6-7
+        for (a, b) in [(1, 2), (2, 3), (3, 4), (4, 5), (6, 7)] {
+            graph.add_edge(a, b);
+        }
+        graph.add_node(Region { code: 8, poor_health: 0.1 }); //isolated
+        let stats = distance_stats(&graph);
+        assert_eq!(stats.eccentricity[&1], 4);
+        assert_eq!(stats.eccentricity[&3], 2);
+        assert_eq!(stats.eccentricity[&6], 1);
+        assert_eq!(stats.eccentricity[&8], 0);
+        assert_eq!((stats.diameter, stats.radius), (4, 1));
+        assert_eq!(stats.center, vec![6, 7]);
+        assert_eq!(stats.periphery, vec![1, 5]);
+        assert_eq!(stats.histogram.into_iter().collect::<Vec<_>>(), vec![(1, 5), (2, 3), (3, 2), (4, 1)]);
+        //the histogram gives the average shortest path length
+        let graph = build_scored_graph(&random_people(200, 6), 0.85);
+        let stats = distance_stats(&graph);
+        assert!((stats.average() - average_shortest_path_length(&graph)).abs() < 1e-12);
+        assert_eq!(stats.pairs() + connected_components(&graph).unreachable_pairs(), 200 * 199 / 2);
+        assert!(stats.center.iter().all(|id| stats.eccentricity[id] == stats.radius));
+    }
+    //Tests the centrality measures on a star, where the hub bridges every pair of leaves
+    #[test]
+    fn test_centrality() {
+        let mut graph: HealthGraph<Region> = HealthGraph::new();
+        for code in 1..=6 {
+            graph.add_node(Region { code, poor_health: 0.1 });
+        }
+        for leaf in 2..=5 {
+            graph.add_edge(1, leaf);
+        }
+        //6 is isolated
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        let betweenness = centrality::betweenness(&graph);
+        assert!(close(betweenness[&1], 6.0 / 10.0)); //6 pairs of leaves out of the 5 * 4 / 2 pairs of the other nodes
+        assert_eq!((betweenness[&2], betweenness[&6]), (0.0, 0.0));
+        let closeness = centrality::closeness(&graph);
+        assert!(close(closeness[&1], (4.0 / 4.0) * (4.0 / 5.0)));
+        assert!(close(closeness[&2], (4.0 / 7.0) * (4.0 / 5.0)));
+        assert_eq!(closeness[&6], 0.0);
+        let harmonic = centrality::harmonic(&graph);
+        assert!(close(harmonic[&1], 4.0 / 5.0));
+        assert!(close(harmonic[&2], (1.0 + 3.0 / 2.0) / 5.0));
+        //the leading eigenvector of a star is 1/sqrt(2) for the hub and half of it for the leaves
+        let eigenvector = centrality::eigenvector(&graph);
+        assert!((eigenvector[&1] - 0.5f64.sqrt()).abs() < 1e-6);
+        assert!((eigenvector[&3] - 0.5f64.sqrt() / 2.0).abs() < 1e-6);
+        assert!(eigenvector[&6].abs() < 1e-6);
+        let pagerank = centrality::pagerank(&graph, centrality::DEFAULT_DAMPING);
+        assert!(close(pagerank.values().sum(), 1.0));
+        assert!(pagerank[&1] > pagerank[&2] && pagerank[&2] > pagerank[&6]);
+        assert!(close(pagerank[&2], pagerank[&5]));
+        //ties go to the smallest id
+        assert_eq!(top_k(&harmonic, 3).into_iter().map(|(id, _)| id).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(top_k(&harmonic, 10).len(), 6);
+        //the measures give the same scores on the CSR form
+        let graph = build_scored_graph(&random_people(150, 7), 0.85);
+        let csr = CsrGraph::from(&graph);
+        assert_eq!(centrality::betweenness(&csr), centrality::betweenness(&graph));
+        assert_eq!(centrality::pagerank(&csr, 0.85), centrality::pagerank(&graph, 0.85));
+    }
+    //Tests Louvain and label propagation on two cliques joined by one edge, and the health of the communities
+    #[test]
+    fn test_communities() {
+        let mut graph: HealthGraph<Region> = HealthGraph::new();
+        for code in 1..=9 {
+            graph.add_node(Region { code, poor_health: 0.1 });
+        }
+        for clique in [[1, 2, 3, 4], [5, 6, 7, 8]] {
+            for (i, &a) in clique.iter().enumerate() {
+                for &b in &clique[i + 1..] {
+                    graph.add_edge(a, b);
+                }
+            }
+        }
+        graph.add_edge(4, 5); //9 is isolated
+        //12 of the 13 edges are inside a community, each community holds half of the degrees
+        let expected = 12.0 / 13.0 - 2.0 * 0.25;
+        for communities in [louvain(&graph), label_propagation(&graph, &mut ChaCha8Rng::seed_from_u64(8))] {
+            assert_eq!(communities.count(), 3);
+            assert_eq!(communities.sizes, vec![4, 4, 1]);
+            assert_eq!(communities.members(0), vec![1, 2, 3, 4]);
+            assert_eq!(communities.members(1), vec![5, 6, 7, 8]);
+            assert_eq!(communities.community(9), Some(2));
+            assert!((communities.modularity - expected).abs() < 1e-12);
+        }
+        let together: HashMap<u32, usize> = (1..=9).map(|code| (code, 0)).collect();
+        assert!(modularity(&graph, &together).abs() < 1e-12);
+        //the communities of people, with the health rates of their members
+        let people = random_people(300, 9);
+        let graph = build_scored_graph(&people, 0.85);
+        let communities = louvain(&graph);
+        assert!(communities.modularity > 0.0);
+        assert_eq!(communities.sizes.iter().sum::<usize>(), 300);
+        let seeded = |seed| label_propagation(&graph, &mut ChaCha8Rng::seed_from_u64(seed)).community_of;
+        assert_eq!(seeded(10), seeded(10));
+        let health = health_by_community(&graph, &communities);
+        assert_eq!(health.len(), communities.count());
+        let members: Vec<&PersonNode> = communities.members(0).iter().map(|id| &graph.nodes[id]).collect();
+        let rates = HealthRates::of(&members);
+        assert_eq!(health[0].rates, rates);
+    }
+    //Tests the health rates of a group and how they are written
+    #[test]
+    fn test_health_rates() {
+        let people: Vec<PersonNode> = random_people(15, 11).into_iter().enumerate().map(|(i, person)| PersonNode {
+            high_bp: Valid(i < 11),
+            high_cholesterol: Valid(i < 9),
+            diabetic: Response::Missing(Missing::NotStated),
+            ..person
+        }).collect();
+        let rates = HealthRates::of(&people.iter().collect::<Vec<_>>());
+        assert_eq!((rates.n, rates.high_cholesterol, rates.diabetic), (15, Some(60.0), None));
+        let json = serde_json::to_string(&rates).unwrap();
+        assert_eq!(json, r#"{"n":15,"high_bp":73.33333333333333,"high_cholesterol":60.0,"diabetic":null}"#);
+        let mut csv_writer = csv::Writer::from_writer(Vec::new());
+        csv_writer.serialize(rates).unwrap();
+        let csv = String::from_utf8(csv_writer.into_inner().unwrap()).unwrap();
+        assert_eq!(csv, "n,high_bp,high_cholesterol,diabetic\n15,73.33333333333333,60.0,\n");
+    }
+    //Tests that the parallel builders and path statistics give the same results as the serial ones
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel() {
+        use crate::parallel;
+        let people = random_people(400, 2);
+        let serial = build_graph(&people);
+        assert_eq!(parallel::build_graph(&people).edges, serial.edges);
+        assert_eq!(parallel::build_graph_with(&people, is_similar).edges, serial.edges);
+        let scored = build_scored_graph(&people, 0.8);
+        let parallel_scored = parallel::build_scored_graph(&people, 0.8);
+        assert_eq!(parallel_scored.edges, scored.edges);
+        assert_eq!(parallel::graph_stats(&serial), graph_stats(&serial));
+        assert_eq!(parallel::average_shortest_path_length(&scored), average_shortest_path_length(&scored));
+        let estimate = parallel::estimate_average_shortest_path_length(&scored, 50, &mut ChaCha8Rng::seed_from_u64(5));
+        assert_eq!(estimate, estimate_average_shortest_path_length(&scored, 50, &mut ChaCha8Rng::seed_from_u64(5)));
+    }
+}